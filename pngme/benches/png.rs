@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pngme::chunk::Chunk;
+use pngme::chunk_type::ChunkType;
+use pngme::png::Png;
+use std::fs;
+use std::str::FromStr;
+
+fn small_png() -> Vec<u8> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/minimal.png");
+    fs::read(path).unwrap()
+}
+
+fn chunk(chunk_type: &str, data: Vec<u8>) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data)
+}
+
+/// A PNG with `count` chunks of `len` bytes each between IHDR and IEND, standing in
+/// for large screenshots (few huge IDATs) or chunk-heavy files (many small chunks).
+fn synthetic_png(chunk_type: &str, count: usize, len: usize) -> Png {
+    let mut chunks = vec![chunk("IHDR", vec![0, 0, 16, 0, 0, 0, 16, 0, 8, 6, 0, 0, 0])];
+    for _ in 0..count {
+        chunks.push(chunk(chunk_type, vec![0xAB; len]));
+    }
+    chunks.push(chunk("IEND", Vec::new()));
+    Png::from_chunks(chunks)
+}
+
+fn bench_parse_and_serialize(c: &mut Criterion) {
+    let fixtures = [
+        ("small", small_png()),
+        ("large", synthetic_png("IDAT", 256, 64 * 1024).as_bytes()),
+    ];
+    for (name, bytes) in fixtures.iter() {
+        let mut group = c.benchmark_group(*name);
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function("try_from", |b| {
+            b.iter(|| Png::try_from(black_box(bytes.as_slice())).unwrap())
+        });
+        let png = Png::try_from(bytes.as_slice()).unwrap();
+        group.bench_function("as_bytes", |b| b.iter(|| black_box(&png).as_bytes()));
+        group.finish();
+    }
+}
+
+fn bench_chunk_lookup(c: &mut Criterion) {
+    let mut png = synthetic_png("tEXt", 10_000, 16);
+    png.append_chunk(chunk("ruSt", b"needle".to_vec()));
+    c.bench_function("chunk_by_type_10k_chunks", |b| {
+        b.iter(|| png.chunk_by_type(black_box("ruSt")).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_parse_and_serialize, bench_chunk_lookup);
+criterion_main!(benches);
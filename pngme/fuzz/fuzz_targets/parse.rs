@@ -0,0 +1,26 @@
+//! Feeds arbitrary bytes to the PNG and chunk parsers. Any input may be rejected, but
+//! none may panic.
+//!
+//! Run with a nightly toolchain and cargo-fuzz (`cargo install cargo-fuzz`):
+//!
+//!     cd pngme
+//!     cargo +nightly fuzz run parse
+//!
+//! Seeding the corpus with the test fixtures gets to interesting inputs faster:
+//!
+//!     mkdir -p fuzz/corpus/parse && cp tests/fixtures/*.png fuzz/corpus/parse/
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pngme::chunk::Chunk;
+use pngme::png::Png;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(png) = Png::try_from(data) {
+        // Anything that parses must serialize back to the same bytes.
+        assert_eq!(png.as_bytes(), data);
+    }
+    let _ = Png::from_bytes_lenient(data);
+    let _ = Chunk::try_from(data);
+    let _ = Chunk::from_bytes_lenient(data);
+});
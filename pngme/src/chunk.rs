@@ -6,8 +6,9 @@ use std::error::Error;
 use std::fmt;
 use std::fmt::Display;
 use std::io::{BufReader, Read};
+use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Chunk {
     length: u32,
     chunk_type: ChunkType,
@@ -16,15 +17,76 @@ pub struct Chunk {
 }
 
 impl Chunk {
+    /// Shorthand for `try_new` with data known to fit.
+    ///
+    /// # Panics
+    ///
+    /// If the data is longer than `u32::MAX` bytes. Use `try_new` for data of
+    /// unknown size.
     pub fn new(chunk_type: ChunkType, chunk_data: Vec<u8>) -> Self {
-        Chunk {
-            length: chunk_data.len() as u32,
-            chunk_type: chunk_type.clone(),
-            chunk_data: chunk_data.clone(),
-            crc: crc::crc32::checksum_ieee(
-                &[chunk_type.bytes().as_slice(), chunk_data.as_slice()].concat(),
-            ),
+        Chunk::try_new(chunk_type, chunk_data).expect("chunk data too long for a PNG chunk")
+    }
+
+    /// Builds a chunk, computing its CRC and taking ownership of the data. Errors if the
+    /// data is too long for the on-disk u32 length field.
+    pub fn try_new(chunk_type: ChunkType, chunk_data: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        let length = checked_length(chunk_data.len())?;
+        let crc = crc_for(&chunk_type, &chunk_data);
+        Ok(Chunk {
+            length,
+            chunk_type,
+            chunk_data,
+            crc,
+        })
+    }
+
+    /// Builds a chunk for hidden data, insisting on an ancillary, safe-to-copy type
+    /// such as `ruSt`. Decoders skip ancillary chunks they don't know, and editors keep
+    /// safe-to-copy ones when they rewrite the image, so the data survives both.
+    pub fn new_ancillary(chunk_type: &str, chunk_data: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        let parsed = ChunkType::from_str(chunk_type)?;
+        if parsed.is_critical() {
+            return Err(ChunkError {
+                err: format!(
+                    "{} is a critical chunk type; lowercase the first letter to make it \
+                     ancillary (e.g. {})",
+                    chunk_type,
+                    chunk_type[..1].to_ascii_lowercase() + &chunk_type[1..]
+                ),
+            }
+            .into());
         }
+        if !parsed.is_safe_to_copy() {
+            return Err(ChunkError {
+                err: format!(
+                    "{} is not safe to copy, so editors may drop it; lowercase the last \
+                     letter (e.g. {})",
+                    chunk_type,
+                    chunk_type[..3].to_owned() + &chunk_type[3..].to_ascii_lowercase()
+                ),
+            }
+            .into());
+        }
+        Chunk::try_new(parsed, chunk_data)
+    }
+
+    /// Builds a chunk that stores `crc` verbatim instead of computing it. Only meant for
+    /// producing deliberately corrupt files to test other tools' validation: a chunk whose
+    /// CRC does not match its type and data violates the PNG spec, and pngme itself will
+    /// refuse to read it back. Only the CRC is unchecked: like `try_new`, this errors if
+    /// the data is too long for the length field.
+    pub fn new_unchecked(
+        chunk_type: ChunkType,
+        chunk_data: Vec<u8>,
+        crc: u32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let length = checked_length(chunk_data.len())?;
+        Ok(Chunk {
+            length,
+            chunk_type,
+            chunk_data,
+            crc,
+        })
     }
 
     pub fn length(&self) -> u32 {
@@ -35,21 +97,68 @@ impl Chunk {
         &self.chunk_type
     }
 
+    /// The type as a `&str`; see [`ChunkType::as_str`] for when that's `None`.
+    pub fn chunk_type_str(&self) -> Option<&str> {
+        self.chunk_type.as_str()
+    }
+
+    /// The type with its description, e.g. `IHDR: image header`, or
+    /// `ruSt: (private/unknown)` for types pngme has no label for.
+    pub fn type_name_description(&self) -> String {
+        format!(
+            "{}: {}",
+            self.chunk_type,
+            self.chunk_type.description().unwrap_or("(private/unknown)")
+        )
+    }
+
+    pub fn type_code(&self) -> u32 {
+        self.chunk_type.type_code()
+    }
+
+    pub fn is_ihdr(&self) -> bool {
+        self.type_code() == ChunkType::IHDR_CODE
+    }
+
+    pub fn is_iend(&self) -> bool {
+        self.type_code() == ChunkType::IEND_CODE
+    }
+
     pub fn crc(&self) -> u32 {
         self.crc
     }
 
+    /// The CRC computed from the type and data. Differs from [`Chunk::crc`] only for
+    /// chunks read leniently from a damaged file or built with `new_unchecked`.
+    pub fn expected_crc(&self) -> u32 {
+        crc_for(&self.chunk_type, &self.chunk_data)
+    }
+
+    pub fn crc_is_valid(&self) -> bool {
+        self.crc == self.expected_crc()
+    }
+
+    /// Changes the type in place, refreshing the CRC since it covers the type bytes.
+    pub fn set_chunk_type(&mut self, chunk_type: ChunkType) {
+        self.crc = crc_for(&chunk_type, &self.chunk_data);
+        self.chunk_type = chunk_type;
+    }
+
+    /// Replaces the data in place, refreshing the length and CRC. Errors (leaving the
+    /// chunk untouched) if the data is too long for the u32 length field.
+    pub fn set_data(&mut self, chunk_data: Vec<u8>) -> Result<(), ChunkError> {
+        self.length = checked_length(chunk_data.len())?;
+        self.crc = crc_for(&self.chunk_type, &chunk_data);
+        self.chunk_data = chunk_data;
+        Ok(())
+    }
+
     pub fn data(&self) -> &[u8] {
         &self.chunk_data.as_slice()
     }
 
     pub fn data_as_string(&self) -> Result<String, Box<dyn Error>> {
-        let mut v: Vec<u8> = Vec::new();
-        for byte in self.chunk_data.iter() {
-            v.push(*byte);
-        }
-        let string = String::from_utf8(v).expect("Our bytes should be valid utf8");
-        return Ok(string);
+        Ok(String::from_utf8(self.chunk_data.clone())?)
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
@@ -62,15 +171,47 @@ impl Chunk {
             .copied()
             .collect::<Vec<u8>>()
     }
+
+    /// Same bytes as `as_bytes`, but reuses the data buffer instead of copying it into
+    /// a fresh one. The data still shifts over to make room for the length and type.
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = self.chunk_data;
+        bytes.reserve(12);
+        let mut prefix = [0u8; 8];
+        prefix[..4].copy_from_slice(&self.length.to_be_bytes());
+        prefix[4..].copy_from_slice(&self.chunk_type.bytes());
+        bytes.splice(0..0, prefix);
+        bytes.extend_from_slice(&self.crc.to_be_bytes());
+        bytes
+    }
 }
 
+/// The CRC a chunk with this type and data must carry: CRC-32 over the type bytes
+/// followed by the data, as the spec defines it. The same value `Chunk::new` stores, for
+/// building or checking chunks without constructing one.
+pub fn crc_for(chunk_type: &ChunkType, chunk_data: &[u8]) -> u32 {
+    crc::crc32::checksum_ieee(&[chunk_type.bytes().as_slice(), chunk_data].concat())
+}
+
+fn checked_length(len: usize) -> Result<u32, ChunkError> {
+    u32::try_from(len).map_err(|_| ChunkError {
+        err: format!("chunk data length {} exceeds max size of u32", len),
+    })
+}
+
+/// Shows the CRC in hex (`Crc: 0xABCD1234`) like other PNG tools; the alternate form
+/// `{:#}` shows it in decimal instead.
 impl fmt::Display for Chunk {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Chunk {{",)?;
         writeln!(f, "  Length: {}", self.length())?;
         writeln!(f, "  Type: {}", self.chunk_type())?;
         writeln!(f, "  Data: {} bytes", self.data().len())?;
-        writeln!(f, "  Crc: {}", self.crc())?;
+        if f.alternate() {
+            writeln!(f, "  Crc: {}", self.crc())?;
+        } else {
+            writeln!(f, "  Crc: 0x{:08X}", self.crc())?;
+        }
         writeln!(f, "}}",)?;
         Ok(())
     }
@@ -79,6 +220,18 @@ impl fmt::Display for Chunk {
 impl TryFrom<&[u8]> for Chunk {
     type Error = Box<dyn Error>;
     fn try_from(chunk: &[u8]) -> Result<Self, Self::Error> {
+        Chunk::parse(chunk, true)
+    }
+}
+
+impl Chunk {
+    /// Parses like `try_from` but keeps the stored CRC even when it doesn't match, for
+    /// recovering data from damaged files. Check [`Chunk::crc_is_valid`] afterwards.
+    pub fn from_bytes_lenient(chunk: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Chunk::parse(chunk, false)
+    }
+
+    fn parse(chunk: &[u8], verify_crc: bool) -> Result<Self, Box<dyn Error>> {
         //length is first four bytes of array
         //need to check if overflow
         if chunk.len() < 12 {
@@ -108,21 +261,11 @@ impl TryFrom<&[u8]> for Chunk {
             }));
         }
 
-        let mut i = 0;
-        //push chunk_data into a vector v
-        let mut v: Vec<u8> = Vec::new();
-        let mut crc_bytes: Vec<u8> = Vec::new();
-
-        //push (length) number of bytes onto chunk_data vector
-        //push every byte after that onto crc
-        for byte in chunk.iter() {
-            if i >= 8 && i < 8 + len {
-                v.push(*byte);
-            } else if i >= 8 + len {
-                crc_bytes.push(*byte);
-            }
-            i += 1;
-        }
+        //(length) bytes after the type are chunk_data, everything after that is crc
+        //clamp to the buffer so a huge declared length can't overflow or slice out of range
+        let data_end = 8usize.saturating_add(len as usize).min(chunk.len());
+        let v: Vec<u8> = chunk[8..data_end].to_vec();
+        let crc_bytes: &[u8] = &chunk[data_end..];
 
         //need to check if length corresponds to chunk_data length
         if v.len() != len.try_into()? {
@@ -131,11 +274,21 @@ impl TryFrom<&[u8]> for Chunk {
             }));
         }
 
+        //exactly four bytes must be left over for the crc
+        if crc_bytes.len() != 4 {
+            return Err(Box::new(ChunkError {
+                err: format!(
+                    "malformed chunk: expected 4 CRC bytes, found {}",
+                    crc_bytes.len()
+                ),
+            }));
+        }
+
         //need to combine chunk type and chunk data into one array for the crc
         let crc_fin = crc::crc32::checksum_ieee(&[chunk_type.as_slice(), v.as_slice()].concat());
         //check crc from leftover bytes against crc calc'd from chunk_type + chunk_data
-        let crc_from_crc_bytes = u32::from_be_bytes(crc_bytes.as_slice().try_into()?);
-        if crc_fin != crc_from_crc_bytes {
+        let crc_from_crc_bytes = u32::from_be_bytes(crc_bytes.try_into()?);
+        if verify_crc && crc_fin != crc_from_crc_bytes {
             return Err(Box::new(ChunkError {
                 err: "expected crc does not match the actual crc".to_owned(),
             }));
@@ -145,7 +298,7 @@ impl TryFrom<&[u8]> for Chunk {
             length: len,
             chunk_type: chunk_type_fin,
             chunk_data: v,
-            crc: crc_fin,
+            crc: crc_from_crc_bytes,
         })
     }
 }
@@ -205,6 +358,125 @@ mod tests {
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_try_new_chunk() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "This is where your secret message will be!"
+            .as_bytes()
+            .to_vec();
+        let chunk = Chunk::try_new(chunk_type, data).unwrap();
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_zero_length_chunk_round_trip() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), Vec::new());
+        assert_eq!(chunk.length(), 0);
+        // With no data the CRC covers the four type bytes alone.
+        assert_eq!(chunk.crc(), crc::crc32::checksum_ieee(b"ruSt"));
+        assert_eq!(chunk.crc(), 0x74B6_A602);
+
+        let bytes = chunk.as_bytes();
+        assert_eq!(bytes.len(), 12);
+        let parsed = Chunk::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(parsed.data(), b"");
+        assert_eq!(parsed.crc(), chunk.crc());
+        assert_eq!(parsed.data_as_string().unwrap(), "");
+
+        // A zero length followed by stray data bytes must not parse.
+        let mut padded = bytes[..8].to_vec();
+        padded.extend_from_slice(b"x");
+        padded.extend_from_slice(&bytes[8..]);
+        assert!(Chunk::try_from(padded.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_type_name_description() {
+        let chunk = Chunk::new(ChunkType::from_str("IDAT").unwrap(), Vec::new());
+        assert_eq!(chunk.type_name_description(), "IDAT: image data");
+        assert_eq!(
+            testing_chunk().type_name_description(),
+            "RuSt: (private/unknown)"
+        );
+    }
+
+    #[test]
+    fn test_crc_for() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = b"This is where your secret message will be!";
+        assert_eq!(crc_for(&chunk_type, data), 2882656334);
+        assert_eq!(crc_for(&chunk_type, data), testing_chunk().crc());
+        let iend = ChunkType::from_str("IEND").unwrap();
+        assert_eq!(crc_for(&iend, &[]), 0xAE426082);
+    }
+
+    #[test]
+    fn test_try_new_moves_data() {
+        let data = b"no copy".to_vec();
+        let ptr = data.as_ptr();
+        let chunk = Chunk::try_new(ChunkType::from_str("RuSt").unwrap(), data).unwrap();
+        assert_eq!(chunk.data().as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_new_ancillary() {
+        let chunk = Chunk::new_ancillary("ruSt", b"hidden".to_vec()).unwrap();
+        assert_eq!(chunk.chunk_type_str(), Some("ruSt"));
+        assert_eq!(chunk.data(), b"hidden");
+        assert!(Chunk::new_ancillary("prVt", Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn test_new_ancillary_rejects_critical_and_unsafe_types() {
+        let err = Chunk::new_ancillary("RuSt", Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("e.g. ruSt"), "{}", err);
+        let err = Chunk::new_ancillary("ruST", Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("e.g. ruSt"), "{}", err);
+        assert!(Chunk::new_ancillary("IDAT", Vec::new()).is_err());
+        assert!(Chunk::new_ancillary("ru1t", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_checked_length() {
+        assert_eq!(checked_length(42).unwrap(), 42);
+        assert_eq!(checked_length(u32::MAX as usize).unwrap(), u32::MAX);
+        #[cfg(target_pointer_width = "64")]
+        assert!(checked_length(u32::MAX as usize + 1).is_err());
+    }
+
+    // Allocates 4 GiB, so only run on request: `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    #[cfg(target_pointer_width = "64")]
+    fn test_try_new_oversized_chunk() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = vec![0; u32::MAX as usize + 1];
+        assert!(Chunk::try_new(chunk_type, data).is_err());
+    }
+
+    #[test]
+    fn test_set_chunk_type() {
+        let mut chunk = Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            "This is where your secret message will be!"
+                .as_bytes()
+                .to_vec(),
+        );
+        chunk.set_chunk_type(ChunkType::from_str("RuSt").unwrap());
+        assert_eq!(chunk.chunk_type_str(), Some("RuSt"));
+        assert_eq!(chunk.crc(), 2882656334);
+        assert!(Chunk::try_from(chunk.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_chunk_equality() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type.clone(), b"one".to_vec());
+        assert_eq!(chunk, Chunk::new(chunk_type.clone(), b"one".to_vec()));
+        assert_ne!(chunk, Chunk::new(chunk_type, b"two".to_vec()));
+    }
+
     #[test]
     fn test_chunk_length() {
         let chunk = testing_chunk();
@@ -216,6 +488,16 @@ mod tests {
         let chunk = testing_chunk();
         assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
     }
+    #[test]
+    fn test_chunk_type_str() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.chunk_type_str(), Some("RuSt"));
+    }
+    #[test]
+    fn test_chunk_type_code() {
+        let chunk = testing_chunk();
+        assert_eq!(chunk.type_code(), u32::from_be_bytes(*b"RuSt"));
+    }
     //fails
     #[test]
     fn test_chunk_string() {
@@ -230,6 +512,93 @@ mod tests {
         let chunk = testing_chunk();
         assert_eq!(chunk.crc(), 2882656334);
     }
+    #[test]
+    fn test_from_bytes_lenient_keeps_bad_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let bytes = Chunk::new_unchecked(chunk_type, b"data".to_vec(), 7)
+            .unwrap()
+            .as_bytes();
+        assert!(Chunk::try_from(bytes.as_slice()).is_err());
+
+        let chunk = Chunk::from_bytes_lenient(bytes.as_slice()).unwrap();
+        assert_eq!(chunk.crc(), 7);
+        assert!(!chunk.crc_is_valid());
+        assert_eq!(chunk.data(), b"data");
+        assert!(testing_chunk().crc_is_valid());
+    }
+
+    #[test]
+    fn test_wrong_number_of_crc_bytes() {
+        let bytes = testing_chunk().as_bytes();
+
+        let err = Chunk::try_from(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "malformed chunk: expected 4 CRC bytes, found 3"
+        );
+
+        let mut long = bytes.clone();
+        long.push(0);
+        let err = Chunk::try_from(long.as_slice()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "malformed chunk: expected 4 CRC bytes, found 5"
+        );
+    }
+
+    #[test]
+    fn test_huge_declared_length_is_rejected() {
+        let mut bytes = u32::MAX.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"RuSt");
+        bytes.extend_from_slice(&[0; 8]);
+        assert!(Chunk::try_from(bytes.as_slice()).is_err());
+        assert!(Chunk::from_bytes_lenient(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_is_ihdr_and_is_iend() {
+        let chunk_with = |t: &str| Chunk::new(ChunkType::from_str(t).unwrap(), Vec::new());
+        assert!(chunk_with("IHDR").is_ihdr());
+        assert!(!chunk_with("IHDR").is_iend());
+        assert!(chunk_with("IEND").is_iend());
+        assert!(!chunk_with("IEND").is_ihdr());
+        for other in ["iHDR", "IHDr", "iEND", "IENd", "RuSt"] {
+            assert!(!chunk_with(other).is_ihdr(), "{} is not IHDR", other);
+            assert!(!chunk_with(other).is_iend(), "{} is not IEND", other);
+        }
+    }
+
+    #[test]
+    fn test_into_bytes_matches_as_bytes() {
+        let chunk = testing_chunk();
+        let expected = chunk.as_bytes();
+        assert_eq!(chunk.into_bytes(), expected);
+
+        let empty = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        let expected = empty.as_bytes();
+        assert_eq!(empty.into_bytes(), expected);
+    }
+
+    #[test]
+    fn test_set_data() {
+        let mut chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"old".to_vec());
+        chunk
+            .set_data(b"This is where your secret message will be!".to_vec())
+            .unwrap();
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.crc(), 2882656334);
+        assert!(Chunk::try_from(chunk.as_bytes().as_ref()).is_ok());
+    }
+
+    #[test]
+    fn test_new_unchecked_keeps_given_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::new_unchecked(chunk_type, b"data".to_vec(), 0xdeadbeef).unwrap();
+        assert_eq!(chunk.crc(), 0xdeadbeef);
+        assert_eq!(chunk.length(), 4);
+        assert!(Chunk::try_from(chunk.as_bytes().as_slice()).is_err());
+    }
+
     //fails
     #[test]
     fn test_valid_chunk_from_bytes() {
@@ -299,4 +668,11 @@ mod tests {
 
         let _chunk_string = format!("{}", chunk);
     }
+
+    #[test]
+    pub fn test_chunk_display_crc() {
+        let chunk = testing_chunk();
+        assert!(format!("{}", chunk).contains("  Crc: 0xABD1D84E\n"));
+        assert!(format!("{:#}", chunk).contains("  Crc: 2882656334\n"));
+    }
 }
@@ -7,42 +7,155 @@ use std::fmt;
 use std::result::Result;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Labels for `ChunkType::description`, one per entry in `STANDARD_TYPES`.
+const DESCRIPTIONS: [(&str, &str); 22] = [
+    ("IHDR", "image header"),
+    ("PLTE", "palette"),
+    ("IDAT", "image data"),
+    ("IEND", "image trailer"),
+    ("cHRM", "primary chromaticities"),
+    ("gAMA", "image gamma"),
+    ("iCCP", "embedded ICC profile"),
+    ("sBIT", "significant bits"),
+    ("sRGB", "standard RGB color space"),
+    ("bKGD", "background color"),
+    ("hIST", "palette histogram"),
+    ("tRNS", "transparency"),
+    ("pHYs", "physical pixel dimensions"),
+    ("sPLT", "suggested palette"),
+    ("tIME", "last modification time"),
+    ("iTXt", "international textual data"),
+    ("tEXt", "textual data"),
+    ("zTXt", "compressed textual data"),
+    ("eXIf", "Exif metadata"),
+    ("acTL", "animation control"),
+    ("fcTL", "frame control"),
+    ("fdAT", "frame data"),
+];
+
+/// Bit 5 of each type byte is the difference between an uppercase ASCII letter (bit
+/// clear) and its lowercase counterpart (bit set). The PNG spec encodes the four chunk
+/// properties in that bit of bytes 0 through 3 respectively.
+const PROPERTY_BIT_MASK: u8 = 0b0010_0000;
+
+/// Chunk types defined by the PNG spec and its registered extensions (including APNG).
+const STANDARD_TYPES: [&str; 22] = [
+    "IHDR", "PLTE", "IDAT", "IEND", "cHRM", "gAMA", "iCCP", "sBIT", "sRGB", "bKGD", "hIST", "tRNS",
+    "pHYs", "sPLT", "tIME", "iTXt", "tEXt", "zTXt", "eXIf", "acTL", "fcTL", "fdAT",
+];
+
+/// Ordered by the four bytes, so uppercase (critical, public) letters sort before
+/// lowercase ones: `IDAT` < `IEND` < `ruSt` < `tEXt`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ChunkType {
     bytes: [u8; 4],
 }
 
 impl ChunkType {
+    pub const IHDR_CODE: u32 = u32::from_be_bytes(*b"IHDR");
+    pub const IEND_CODE: u32 = u32::from_be_bytes(*b"IEND");
+
+    /// Wraps the four bytes without checking that they are ASCII letters. Only for
+    /// reproducing unusual or broken files; such types violate the PNG spec and
+    /// [`ChunkType::as_str`] panics if the bytes aren't valid UTF-8.
+    pub fn from_bytes_unchecked(bytes: [u8; 4]) -> Self {
+        Self { bytes }
+    }
+
     pub fn bytes(&self) -> [u8; 4] {
         return self.bytes;
     }
 
+    /// Uppercase first byte.
     pub fn is_critical(&self) -> bool {
-        //bit shift 1 by 5 to
-        return self.bytes[0] & (1 << 5) == 0;
+        return self.bytes[0] & PROPERTY_BIT_MASK == 0;
     }
 
+    /// Uppercase second byte.
     pub fn is_public(&self) -> bool {
-        return self.bytes[1] & (1 << 5) == 0;
+        return self.bytes[1] & PROPERTY_BIT_MASK == 0;
     }
 
+    /// Uppercase third byte; lowercase is reserved by the spec.
     pub fn is_reserved_bit_valid(&self) -> bool {
-        return self.bytes[2] & (1 << 5) == 0;
+        return self.bytes[2] & PROPERTY_BIT_MASK == 0;
     }
 
+    /// Lowercase fourth byte.
     pub fn is_safe_to_copy(&self) -> bool {
-        return self.bytes[3] & (1 << 5) == 1 << 5;
+        return self.bytes[3] & PROPERTY_BIT_MASK == PROPERTY_BIT_MASK;
+    }
+
+    /// Whether this is one of the types defined by the spec, as opposed to a private one.
+    pub fn is_standard(&self) -> bool {
+        STANDARD_TYPES.iter().any(|t| self.matches(t))
+    }
+
+    /// A short human label for standard types, e.g. "physical pixel dimensions" for
+    /// `pHYs`. `None` for private or unknown types.
+    pub fn description(&self) -> Option<&'static str> {
+        DESCRIPTIONS
+            .iter()
+            .find(|(t, _)| self.matches(t))
+            .map(|(_, description)| *description)
+    }
+
+    /// All four property flags at once.
+    pub fn properties(&self) -> ChunkProperties {
+        ChunkProperties {
+            critical: self.is_critical(),
+            public: self.is_public(),
+            reserved_valid: self.is_reserved_bit_valid(),
+            safe_to_copy: self.is_safe_to_copy(),
+        }
     }
 
     pub fn is_valid(&self) -> bool {
         self.is_reserved_bit_valid()
     }
+
+    /// Borrows the type as a `&str`, or `None` if the bytes aren't all ASCII letters,
+    /// which only happens for types built with `from_bytes_unchecked`.
+    pub fn as_str(&self) -> Option<&str> {
+        if !self.bytes.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        std::str::from_utf8(&self.bytes).ok()
+    }
+
+    /// The four type bytes read as a big-endian integer, for cheap comparisons
+    /// against precomputed constants such as [`ChunkType::IEND_CODE`].
+    pub fn type_code(&self) -> u32 {
+        u32::from_be_bytes(self.bytes)
+    }
+
+    /// Compares against a type string without parsing or allocating.
+    pub fn matches(&self, chunk_type: &str) -> bool {
+        self.bytes == chunk_type.as_bytes()
+    }
+
+    /// Like `matches` but ignoring ASCII case, so `rust` matches `ruSt`. Case carries
+    /// the property bits, so only use this for lookups a user typed by hand.
+    pub fn matches_ignore_case(&self, chunk_type: &str) -> bool {
+        self.bytes.eq_ignore_ascii_case(chunk_type.as_bytes())
+    }
+}
+
+/// The property flags encoded in the case of a chunk type's four bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkProperties {
+    pub critical: bool,
+    pub public: bool,
+    pub reserved_valid: bool,
+    pub safe_to_copy: bool,
 }
 
 #[derive(Debug)]
 pub enum ChunkTypeDecodingError {
-    /// We found a bad byte while decoding. The u8 is the first invalid byte found.
+    /// We found a bad byte while decoding. The u8 is the only invalid byte found.
     BadByte(u8),
+    /// We found more than one bad byte. Each entry is the position and the byte found there.
+    BadBytes(Vec<(usize, u8)>),
     /// The chunk type to be decoded was the wrong size. The usize is the received size.
     BadLength(usize),
 }
@@ -51,6 +164,10 @@ impl fmt::Display for ChunkTypeDecodingError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::BadByte(byte) => write!(f, "Bad byte: {byte} ({byte:b})", byte = byte),
+            Self::BadBytes(bad) => {
+                let positions: Vec<String> = bad.iter().map(|(i, _)| i.to_string()).collect();
+                write!(f, "Bad bytes at positions {}", positions.join(","))
+            }
             Self::BadLength(len) => write!(f, "Bad length: {} (expected 4)", len),
         }
     }
@@ -59,10 +176,16 @@ impl fmt::Display for ChunkTypeDecodingError {
 impl std::error::Error for ChunkTypeDecodingError {}
 
 /* */
+/// Writes letters as-is and any other byte as `\xNN`, so a type built with
+/// [`ChunkType::from_bytes_unchecked`] can't put control characters on the terminal.
 impl fmt::Display for ChunkType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for byte in self.bytes.iter() {
-            write!(f, "{}", *byte as char)?;
+            if byte.is_ascii_graphic() {
+                write!(f, "{}", *byte as char)?;
+            } else {
+                write!(f, "\\x{:02x}", byte)?;
+            }
         }
         Ok(())
     }
@@ -71,12 +194,17 @@ impl fmt::Display for ChunkType {
 impl TryFrom<[u8; 4]> for ChunkType {
     type Error = Box<dyn Error>;
     fn try_from(chunk_type: [u8; 4]) -> Result<Self, Self::Error> {
-        for byte in chunk_type.iter() {
-            if !(byte.is_ascii_uppercase() || byte.is_ascii_lowercase()) {
-                return Err(Box::new(ChunkTypeDecodingError::BadByte(*byte)));
-            }
+        let bad: Vec<(usize, u8)> = chunk_type
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, byte)| !byte.is_ascii_alphabetic())
+            .collect();
+        match bad.as_slice() {
+            [] => Ok(Self { bytes: chunk_type }),
+            [(_, byte)] => Err(Box::new(ChunkTypeDecodingError::BadByte(*byte))),
+            _ => Err(Box::new(ChunkTypeDecodingError::BadBytes(bad))),
         }
-        Ok(Self { bytes: chunk_type })
     }
 }
 
@@ -87,17 +215,8 @@ impl FromStr for ChunkType {
         if bytes.len() != 4 {
             return Err(Box::new(ChunkTypeDecodingError::BadLength(s.len())));
         }
-        let mut arr: [u8; 4] = [0, 0, 0, 0];
-        let mut i = 0;
-        for byte in bytes.iter() {
-            if byte.is_ascii_uppercase() || byte.is_ascii_lowercase() {
-                arr[i] = *byte;
-            } else {
-                return Err(Box::new(ChunkTypeDecodingError::BadByte(*byte)));
-            }
-            i += 1;
-        }
-        return Ok(Self { bytes: arr });
+        let arr: [u8; 4] = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Self::try_from(arr)
     }
 }
 
@@ -172,6 +291,91 @@ mod tests {
         assert!(!chunk.is_safe_to_copy());
     }
 
+    #[test]
+    pub fn test_property_bits_are_independent() {
+        // Flipping the case of one byte must only change that byte's property.
+        let base = ChunkType::from_str("ABCD").unwrap();
+        assert!(base.is_critical() && base.is_public() && base.is_reserved_bit_valid());
+        assert!(!base.is_safe_to_copy());
+
+        let chunk = ChunkType::from_str("aBCD").unwrap();
+        assert!(!chunk.is_critical() && chunk.is_public() && chunk.is_reserved_bit_valid());
+        assert!(!chunk.is_safe_to_copy());
+
+        let chunk = ChunkType::from_str("AbCD").unwrap();
+        assert!(chunk.is_critical() && !chunk.is_public() && chunk.is_reserved_bit_valid());
+        assert!(!chunk.is_safe_to_copy());
+
+        let chunk = ChunkType::from_str("ABcD").unwrap();
+        assert!(chunk.is_critical() && chunk.is_public() && !chunk.is_reserved_bit_valid());
+        assert!(!chunk.is_safe_to_copy());
+
+        let chunk = ChunkType::from_str("ABCd").unwrap();
+        assert!(chunk.is_critical() && chunk.is_public() && chunk.is_reserved_bit_valid());
+        assert!(chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_predicates_for_every_case_combination() {
+        // Bit i of `mask` set means byte i of "blob" is lowercase.
+        for mask in 0..16u8 {
+            let lower = |i: u8| mask & (1 << i) != 0;
+            let name: String = "blob"
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if lower(i as u8) {
+                        c
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect();
+            let chunk = ChunkType::from_str(&name).unwrap();
+            assert_eq!(chunk.is_critical(), !lower(0), "{}", name);
+            assert_eq!(chunk.is_public(), !lower(1), "{}", name);
+            assert_eq!(chunk.is_reserved_bit_valid(), !lower(2), "{}", name);
+            assert_eq!(chunk.is_safe_to_copy(), lower(3), "{}", name);
+            assert_eq!(chunk.is_valid(), !lower(2), "{}", name);
+        }
+    }
+
+    #[test]
+    pub fn test_predicates_known_cases() {
+        let blob = ChunkType::from_str("bLOb").unwrap();
+        assert!(!blob.is_critical() && blob.is_public() && blob.is_reserved_bit_valid());
+        assert!(blob.is_safe_to_copy());
+        let blob = ChunkType::from_str("bLOB").unwrap();
+        assert!(!blob.is_critical() && blob.is_public() && blob.is_reserved_bit_valid());
+        assert!(!blob.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_properties_match_predicates() {
+        for name in ["RuSt", "ruSt", "RUSt", "Rust", "RuST", "abcd", "ABCD"] {
+            let chunk = ChunkType::from_str(name).unwrap();
+            let properties = chunk.properties();
+            assert_eq!(properties.critical, chunk.is_critical());
+            assert_eq!(properties.public, chunk.is_public());
+            assert_eq!(properties.reserved_valid, chunk.is_reserved_bit_valid());
+            assert_eq!(properties.safe_to_copy, chunk.is_safe_to_copy());
+        }
+    }
+
+    #[test]
+    pub fn test_is_standard() {
+        for name in ["IHDR", "IDAT", "tEXt", "fcTL"] {
+            assert!(ChunkType::from_str(name).unwrap().is_standard(), "{}", name);
+        }
+        for name in ["ruSt", "RuSt", "text", "idat"] {
+            assert!(
+                !ChunkType::from_str(name).unwrap().is_standard(),
+                "{}",
+                name
+            );
+        }
+    }
+
     #[test]
     pub fn test_valid_chunk_is_valid() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
@@ -187,12 +391,121 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    pub fn test_single_bad_byte() {
+        let err = ChunkType::from_str("Ru1t").unwrap_err();
+        let err = err.downcast_ref::<ChunkTypeDecodingError>().unwrap();
+        assert!(matches!(err, ChunkTypeDecodingError::BadByte(b'1')));
+    }
+
+    #[test]
+    pub fn test_multiple_bad_bytes() {
+        let err = ChunkType::try_from([82, b'1', 83, b' ']).unwrap_err();
+        assert_eq!(err.to_string(), "Bad bytes at positions 1,3");
+        let err = err.downcast_ref::<ChunkTypeDecodingError>().unwrap();
+        match err {
+            ChunkTypeDecodingError::BadBytes(bad) => assert_eq!(bad, &vec![(1, b'1'), (3, b' ')]),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_description() {
+        let description = |t: &str| ChunkType::from_str(t).unwrap().description();
+        assert_eq!(description("IHDR"), Some("image header"));
+        assert_eq!(description("tEXt"), Some("textual data"));
+        assert_eq!(description("pHYs"), Some("physical pixel dimensions"));
+        assert_eq!(description("ruSt"), None);
+        assert_eq!(description("TEXT"), None);
+        for t in STANDARD_TYPES {
+            assert!(description(t).is_some(), "{} has no description", t);
+        }
+    }
+
+    #[test]
+    pub fn test_chunk_type_ordering() {
+        let mut types: Vec<ChunkType> = ["tEXt", "IEND", "ruSt", "IDAT", "IHDR", "rUST"]
+            .iter()
+            .map(|t| ChunkType::from_str(t).unwrap())
+            .collect();
+        types.sort();
+        let sorted: Vec<&str> = types.iter().map(|t| t.as_str().unwrap()).collect();
+        assert_eq!(sorted, ["IDAT", "IEND", "IHDR", "rUST", "ruSt", "tEXt"]);
+        let a = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(a.cmp(&a.clone()), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    pub fn test_matches_ignore_case() {
+        let chunk = ChunkType::from_str("ruSt").unwrap();
+        assert!(chunk.matches_ignore_case("rust"));
+        assert!(chunk.matches_ignore_case("RUST"));
+        assert!(!chunk.matches("rust"));
+        assert!(!chunk.matches_ignore_case("rus"));
+    }
+
+    #[test]
+    pub fn test_display_escapes_unprintable_bytes() {
+        let chunk = ChunkType::from_bytes_unchecked([b'r', 0x1b, b' ', 0xff]);
+        assert_eq!(chunk.to_string(), "r\\x1b\\x20\\xff");
+        assert_eq!(ChunkType::from_str("ruSt").unwrap().to_string(), "ruSt");
+    }
+
+    #[test]
+    pub fn test_from_bytes_unchecked() {
+        let chunk = ChunkType::from_bytes_unchecked([0, 1, 2, 3]);
+        assert_eq!(chunk.bytes(), [0, 1, 2, 3]);
+        assert_eq!(chunk.type_code(), 0x00010203);
+        assert_eq!(chunk.as_str(), None);
+        assert_eq!(
+            ChunkType::from_bytes_unchecked([0, 0xff, 0x10, 0xab]).as_str(),
+            None
+        );
+    }
+
     #[test]
     pub fn test_chunk_type_string() {
         let chunk = ChunkType::from_str("RuSt").unwrap();
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_chunk_type_as_str() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert_eq!(chunk.as_str(), Some("RuSt"));
+    }
+
+    #[test]
+    pub fn test_chunk_type_code() {
+        let chunk = ChunkType::from_str("IEND").unwrap();
+        assert_eq!(chunk.type_code(), 0x49454E44);
+        assert_eq!(chunk.type_code(), ChunkType::IEND_CODE);
+        assert_eq!(
+            ChunkType::from_str("IHDR").unwrap().type_code(),
+            ChunkType::IHDR_CODE
+        );
+    }
+
+    #[test]
+    pub fn test_chunk_type_matches() {
+        let chunk = ChunkType::from_str("RuSt").unwrap();
+        assert!(chunk.matches("RuSt"));
+        assert!(!chunk.matches("rust"));
+        assert!(!chunk.matches("RuStX"));
+    }
+
+    #[test]
+    pub fn test_chunk_type_hash() {
+        use std::collections::HashSet;
+
+        let mut set = HashSet::new();
+        assert!(set.insert(ChunkType::from_str("RuSt").unwrap()));
+        assert!(set.insert(ChunkType::from_str("ruSt").unwrap()));
+        assert!(!set.insert(ChunkType::try_from([82, 117, 83, 116]).unwrap()));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&ChunkType::from_str("RuSt").unwrap()));
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
@@ -1,34 +1,2377 @@
 use crate::chunk::Chunk;
 use crate::chunk::ChunkError;
-use crate::chunk_type::ChunkType;
-use crate::png::Png;
-use std::fs;
+use crate::chunk_type::{ChunkType, ChunkTypeDecodingError};
+use crate::color::{paint, Color};
+use crate::info::{Gamma, ModificationTime, Srgb};
+use crate::png::{OrderError, ParseOptions, Png, PngError};
+use crate::progress::{ProgressReader, ProgressWriter};
+use crate::text::{CompressedTextChunk, TextChunk};
+use log::{debug, error, info, warn};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
 
-pub fn encode(file_path: &str, chunk_type: &str, message: &str) {
-    let mut image = Png::try_from(fs::read(file_path).unwrap().as_slice()).unwrap();
-    let chunk_data = message.as_bytes().to_vec();
-    let actual_chunk_type = ChunkType::from_str(chunk_type).expect("string not four characters");
-    let secret_chunk = Chunk::new(actual_chunk_type, chunk_data);
-    image.append_chunk(secret_chunk);
-    fs::write(file_path, image.as_bytes()).unwrap();
+/// Length of the sequence header written at the start of every chunk produced by
+/// `encode --split`: a big-endian u32 part index followed by a big-endian u32 part count.
+const SPLIT_HEADER_LEN: usize = 8;
+
+/// Marks where `encode --pad-to` padding starts; the rest of the padding is zeros.
+/// Always writing the marker (ISO/IEC 7816-4 style) is what lets `decode --unpad`
+/// tell padding apart from a message that just happens to end in zero bytes.
+const PADDING_MARKER: u8 = 0x80;
+
+/// Block size used when writing with progress enabled, so the percentage moves.
+const PROGRESS_BLOCK_LEN: usize = 64 * 1024;
+
+/// Bytes of chunk data shown by `print --hexdump` unless `--full` is given.
+const HEXDUMP_PREVIEW_LEN: usize = 256;
+
+/// How commands read and write files, shared by every command that touches one.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IoOptions {
+    /// Report a percentage on stderr while reading and writing PNG files.
+    pub progress: bool,
+    /// Make files rewritten in place keep their previous modification time, so backup
+    /// and sync tools that go by mtime don't see them as changed. Only the mtime is
+    /// restored: the access time and, on Unix, the change time (ctime) still move, and
+    /// filesystems with coarse timestamps (FAT has 2-second steps) may round it.
+    pub preserve_mtime: bool,
+}
+
+/// A `file_path` of `-` reads from stdin or writes to stdout, so commands can be piped.
+const STDIO_PATH: &str = "-";
+
+fn read_file(file_path: &str, io_options: IoOptions) -> io::Result<Vec<u8>> {
+    debug!("reading {}", file_path);
+    if file_path == STDIO_PATH {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+    if !io_options.progress {
+        return fs::read(file_path);
+    }
+    let file = File::open(file_path)?;
+    let total = file.metadata()?.len();
+    let mut bytes = Vec::with_capacity(total as usize);
+    ProgressReader::new(file, &format!("Reading {}", file_path), total).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn write_file(file_path: &str, bytes: &[u8], io_options: IoOptions) -> io::Result<()> {
+    debug!("writing {} bytes to {}", bytes.len(), file_path);
+    // A file that doesn't exist yet has no old time to keep.
+    let mtime = if io_options.preserve_mtime && file_path != STDIO_PATH {
+        fs::metadata(file_path).and_then(|m| m.modified()).ok()
+    } else {
+        None
+    };
+    write_contents(file_path, bytes, io_options.progress)?;
+    if let Some(mtime) = mtime {
+        File::options()
+            .write(true)
+            .open(file_path)?
+            .set_modified(mtime)?;
+    }
+    Ok(())
+}
+
+fn write_contents(file_path: &str, bytes: &[u8], progress: bool) -> io::Result<()> {
+    if file_path == STDIO_PATH {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(bytes)?;
+        return stdout.flush();
+    }
+    if !progress {
+        return fs::write(file_path, bytes);
+    }
+    let file = File::create(file_path)?;
+    let label = format!("Writing {}", file_path);
+    let mut writer = ProgressWriter::new(file, &label, bytes.len() as u64);
+    for block in bytes.chunks(PROGRESS_BLOCK_LEN) {
+        writer.write_all(block)?;
+    }
+    writer.flush()
+}
+
+/// Reads and parses a PNG. With `no_crc_check`, chunks with a bad CRC are kept and a
+/// warning naming each one goes to stderr.
+fn read_png(
+    file_path: &str,
+    no_crc_check: bool,
+    offset: usize,
+    io_options: IoOptions,
+) -> Result<Png, Box<dyn Error>> {
+    let bytes = read_file(file_path, io_options)?;
+    let options = ParseOptions {
+        verify_crc: !no_crc_check,
+        offset,
+        ..ParseOptions::default()
+    };
+    let image = Png::try_from_with_options(&bytes, &options)?;
+    if !no_crc_check {
+        return Ok(image);
+    }
+    for (index, chunk) in image.chunks().iter().enumerate() {
+        if !chunk.crc_is_valid() {
+            warn!(
+                "chunk {} ({}) has CRC {:08x}, expected {:08x}",
+                index,
+                chunk.chunk_type(),
+                chunk.crc(),
+                chunk.expected_crc()
+            );
+        }
+    }
+    Ok(image)
+}
+
+/// Exit status for I/O failures and anything not covered by a more specific code.
+pub const EXIT_IO_ERROR: i32 = 1;
+/// Exit status when the input is not a valid PNG, a chunk type is malformed, or the
+/// arguments don't make sense together.
+pub const EXIT_PARSE_ERROR: i32 = 2;
+/// Exit status when the requested chunk type is not present in the file.
+pub const EXIT_CHUNK_NOT_FOUND: i32 = 3;
+
+/// Returned when a command is asked for a chunk type the file doesn't contain, so it
+/// can be told apart from I/O and parse failures.
+#[derive(Debug)]
+pub struct ChunkNotFound {
+    pub chunk_type: String,
+}
+
+impl ChunkNotFound {
+    pub fn new(chunk_type: &str) -> Self {
+        ChunkNotFound {
+            chunk_type: chunk_type.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ChunkNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no {} chunk found", self.chunk_type)
+    }
+}
+
+impl std::error::Error for ChunkNotFound {}
+
+/// Returned for arguments that parse but can't be used, such as a missing chunk type
+/// with no configured default. Exits like a parse error, as clap's own usage errors do.
+#[derive(Debug)]
+pub struct UsageError {
+    pub message: String,
+}
+
+impl UsageError {
+    pub fn new(message: impl Into<String>) -> Self {
+        UsageError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UsageError {}
+
+/// A command's error prefixed with what it was doing and to which file, such as
+/// `encode into a.png: No such file or directory (os error 2)`, so one line on stderr
+/// says what failed. [`exit_code`] looks through it to the original error.
+#[derive(Debug)]
+pub struct CommandError {
+    pub context: String,
+    pub source: Box<dyn Error>,
 }
 
-pub fn decode(file_path: &str, chunk_type: &str) {
-    let mut image = Png::try_from(fs::read(file_path).unwrap().as_slice()).unwrap();
-    let encoded_chunk = image
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Wraps any error in `result` in a [`CommandError`] with the given context.
+pub fn with_context<T>(
+    result: Result<T, Box<dyn Error>>,
+    context: impl FnOnce() -> String,
+) -> Result<T, Box<dyn Error>> {
+    result.map_err(|source| {
+        CommandError {
+            context: context(),
+            source,
+        }
+        .into()
+    })
+}
+
+/// Maps a command error to the documented process exit status.
+pub fn exit_code(err: &(dyn Error + 'static)) -> i32 {
+    if let Some(err) = err.downcast_ref::<CommandError>() {
+        exit_code(err.source.as_ref())
+    } else if err.is::<ChunkNotFound>() {
+        EXIT_CHUNK_NOT_FOUND
+    } else if err.is::<PngError>()
+        || err.is::<ChunkError>()
+        || err.is::<ChunkTypeDecodingError>()
+        || err.is::<UsageError>()
+    {
+        EXIT_PARSE_ERROR
+    } else {
+        EXIT_IO_ERROR
+    }
+}
+
+/// Reads a message for `encode --message-file`. Line endings are normalized to LF so a
+/// file saved on Windows doesn't smuggle carriage returns into the payload.
+pub fn read_message_file(path: &str, io_options: IoOptions) -> Result<String, Box<dyn Error>> {
+    let text = with_context(
+        read_file(path, io_options)
+            .map_err(Box::from)
+            .and_then(|bytes| Ok(String::from_utf8(bytes)?)),
+        || format!("message file {}", path),
+    )?;
+    Ok(strip_cr(&text))
+}
+
+/// Encodes `text` as Latin-1, the charset `tEXt` text is stored in.
+fn latin1_bytes(text: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    text.chars()
+        .map(|c| {
+            u8::try_from(c).map_err(|_| {
+                UsageError::new(format!("tEXt text can only hold Latin-1, not {:?}", c)).into()
+            })
+        })
+        .collect()
+}
+
+/// Drops every `\r` that is immediately followed by `\n`; lone carriage returns stay.
+fn strip_cr(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+#[derive(Debug, Default)]
+pub struct EncodeOptions {
+    /// Spread the message over chunks carrying at most this many message bytes each,
+    /// not counting the `SPLIT_HEADER_LEN`-byte sequence header every part starts with.
+    pub split: Option<usize>,
+    /// Store this CRC instead of computing one. The resulting file violates the PNG spec;
+    /// this exists only to produce test inputs for other tools.
+    pub raw_crc: Option<u32>,
+    /// Write a `tEXt` chunk as `keyword\0message`. Only valid with the `tEXt` type.
+    pub keyword: Option<String>,
+    /// Write the result to stdout instead of back to `file_path`.
+    pub stdout: bool,
+    /// Pad the data with a marker and zeros to a multiple of this many bytes.
+    pub pad_to: Option<usize>,
+    /// What to do when a chunk of the type already exists. `None` appends regardless.
+    pub if_absent: Option<IfAbsent>,
+    /// Don't warn about encoding into a critical or standard chunk type.
+    pub force: bool,
+    /// `chunk_type` is 8 hex digits giving the type bytes directly, skipping validation.
+    pub type_hex: bool,
+    /// Do nothing if the file already holds exactly this payload under this type.
+    pub skip_duplicate: bool,
+    /// Insert after the first chunk of this type instead of appending.
+    pub after: Option<String>,
+    /// Insert before the first chunk of this type instead of appending.
+    pub before: Option<String>,
+    /// Overwrite `file_path` without asking, even on a terminal.
+    pub force_overwrite: bool,
+    /// Set the `tIME` chunk to now, replacing an existing one.
+    pub timestamp: bool,
+    /// How the file is read (and written back, where the command does).
+    pub io: IoOptions,
+}
+
+/// The `encode --if-absent` behavior when the file already has a chunk of the type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfAbsent {
+    /// Leave the file untouched and say so on stderr.
+    Skip,
+    /// Fail without writing.
+    Error,
+}
+
+impl FromStr for IfAbsent {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(IfAbsent::Skip),
+            "error" => Ok(IfAbsent::Error),
+            other => Err(format!(
+                "unknown --if-absent behavior {} (expected skip or error)",
+                other
+            )),
+        }
+    }
+}
+
+pub fn encode(
+    file_path: &str,
+    chunk_type: &str,
+    message: &str,
+    options: &EncodeOptions,
+) -> Result<(), Box<dyn Error>> {
+    let bytes = read_file(file_path, options.io)?;
+    let mut image = Png::try_from(bytes.as_slice())?;
+    let actual_chunk_type = if options.type_hex {
+        parse_type_hex(chunk_type)?
+    } else {
+        ChunkType::from_str(chunk_type)?
+    };
+    let exists = image
+        .chunks()
+        .iter()
+        .any(|c| c.chunk_type() == &actual_chunk_type);
+    if let (Some(if_absent), true) = (options.if_absent, exists) {
+        if if_absent == IfAbsent::Error {
+            return Err(format!("file already has a {} chunk", chunk_type).into());
+        }
+        let reason = format!("{} already has a {} chunk", file_path, chunk_type);
+        return skip_encode(&reason, &bytes, options);
+    }
+    // A keyword means the caller is deliberately writing real tEXt metadata.
+    if !options.force && options.keyword.is_none() {
+        warn_if_risky_type(&actual_chunk_type);
+    }
+    let chunk_data = match &options.keyword {
+        Some(_) if !actual_chunk_type.matches("tEXt") => {
+            return Err(UsageError::new(format!(
+                "--keyword only applies to tEXt chunks, not {}",
+                chunk_type
+            ))
+            .into());
+        }
+        Some(_) if options.split.is_some() => {
+            return Err(UsageError::new("--keyword cannot be combined with --split").into());
+        }
+        Some(keyword) => TextChunk::new(keyword, latin1_bytes(message)?)?.as_bytes(),
+        None => message.as_bytes().to_vec(),
+    };
+    let chunk_data = match options.pad_to {
+        Some(_) if options.split.is_some() || options.keyword.is_some() => {
+            return Err("--pad-to cannot be combined with --split or --keyword".into());
+        }
+        Some(multiple) => pad_payload(chunk_data, multiple)?,
+        None => chunk_data,
+    };
+    let parts = match options.split {
+        Some(part_size) => split_payload(&chunk_data, part_size)?,
+        None => vec![chunk_data],
+    };
+    let anchor = match (&options.before, &options.after) {
+        (Some(_), Some(_)) => return Err("--before cannot be combined with --after".into()),
+        (Some(anchor), None) | (None, Some(anchor)) => Some(anchor),
+        (None, None) => None,
+    };
+    if let Some(anchor) = anchor {
+        if image.index_of_type(anchor).is_none() {
+            return Err(ChunkNotFound::new(anchor).into());
+        }
+    }
+    let mut new_chunks = Vec::with_capacity(parts.len());
+    for part in parts {
+        new_chunks.push(match options.raw_crc {
+            Some(crc) => Chunk::new_unchecked(actual_chunk_type.clone(), part, crc)?,
+            None => Chunk::try_new(actual_chunk_type.clone(), part)?,
+        });
+    }
+    if options.skip_duplicate && holds_payload(&image, &new_chunks) {
+        let reason = format!("{} already holds this {} payload", file_path, chunk_type);
+        return skip_encode(&reason, &bytes, options);
+    }
+    // The first chunk goes next to the anchor and the rest follow it in order.
+    let mut last_inserted = None;
+    for chunk in new_chunks {
+        last_inserted = Some(match (last_inserted, &options.before, &options.after) {
+            (Some(index), _, _) => {
+                image.insert_chunk(index + 1, chunk)?;
+                index + 1
+            }
+            (None, Some(anchor), _) => image.insert_before_type(anchor, chunk)?,
+            (None, None, Some(anchor)) => image.insert_after_type(anchor, chunk)?,
+            (None, None, None) => {
+                image.append_chunk(chunk);
+                continue;
+            }
+        });
+    }
+    if options.timestamp {
+        let time = ModificationTime::now().to_chunk();
+        match image.index_of_type("tIME") {
+            Some(index) => {
+                image.replace_chunk(index, time)?;
+            }
+            // finalize moves IEND back to the end, so this lands just before it.
+            None => image.append_chunk(time),
+        }
+    }
+    image.finalize()?;
+    let output = if options.stdout {
+        STDIO_PATH
+    } else {
+        file_path
+    };
+    if output != STDIO_PATH && !options.force_overwrite && is_interactive() {
+        confirm_overwrite(output)?;
+    }
+    write_file(output, &image.as_bytes(), options.io)?;
+    Ok(())
+}
+
+/// Someone is at the keyboard: both stdin and stdout are terminals. Scripts and pipes
+/// never get prompted.
+fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Asks on stderr before `encode` replaces `file_path`, erroring unless the answer is
+/// yes so the file is left alone.
+fn confirm_overwrite(file_path: &str) -> Result<(), Box<dyn Error>> {
+    eprint!("This will overwrite {}. Continue? [y/N] ", file_path);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if is_yes(&answer) {
+        Ok(())
+    } else {
+        Err(format!("aborted; {} was not modified", file_path).into())
+    }
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Text encodings `decode` can interpret chunk data as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Charset {
+    /// Decodes `bytes` as text. Data that isn't valid in this charset is a [`PngError`],
+    /// so it exits as a parse error.
+    pub fn decode(self, bytes: &[u8]) -> Result<String, Box<dyn Error>> {
+        let (to_unit, name): (fn([u8; 2]) -> u16, &str) = match self {
+            Charset::Utf8 => {
+                return String::from_utf8(bytes.to_vec())
+                    .map_err(|e| PngError::new(format!("data is not valid utf8: {}", e)).into())
+            }
+            // Latin-1 code points are exactly the byte values.
+            Charset::Latin1 => return Ok(bytes.iter().map(|b| *b as char).collect()),
+            Charset::Utf16Le => (u16::from_le_bytes, "utf16le"),
+            Charset::Utf16Be => (u16::from_be_bytes, "utf16be"),
+        };
+        let pairs = bytes.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return Err(PngError::new(format!(
+                "{} data must have an even length, found {} bytes",
+                name,
+                bytes.len()
+            ))
+            .into());
+        }
+        let units: Vec<u16> = pairs.map(|pair| to_unit([pair[0], pair[1]])).collect();
+        String::from_utf16(&units)
+            .map_err(|e| PngError::new(format!("data is not valid {}: {}", name, e)).into())
+    }
+}
+
+impl FromStr for Charset {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Charset::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Charset::Latin1),
+            "utf16le" | "utf-16le" => Ok(Charset::Utf16Le),
+            "utf16be" | "utf-16be" => Ok(Charset::Utf16Be),
+            other => Err(format!(
+                "unknown charset {} (expected utf8, latin1, utf16le or utf16be)",
+                other
+            )),
+        }
+    }
+}
+
+/// How `for_each_file` runs one command over several files.
+#[derive(Debug, Default)]
+pub struct BatchOptions {
+    /// Write results here under the inputs' file names instead of changing the inputs.
+    pub output_dir: Option<String>,
+    /// Carry on after a file fails instead of stopping at the first error.
+    pub keep_going: bool,
+}
+
+/// Runs an in-place command such as `encode` or `remove` on each file, then prints a
+/// line per file and a total. With an output directory, each input is first copied
+/// there and the copy is changed, so the originals stay as they were; a copy whose
+/// command fails is deleted rather than left looking like a result.
+pub fn for_each_file(
+    files: &[String],
+    batch: &BatchOptions,
+    op: impl Fn(&str) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut outcomes = Vec::new();
+    let mut used_names = Vec::new();
+    for file in files {
+        let result = match &batch.output_dir {
+            Some(dir) => batch_output(file, dir, &mut used_names).and_then(|target| {
+                let result = op(&target);
+                if result.is_err() {
+                    let _ = fs::remove_file(&target);
+                }
+                result
+            }),
+            None => op(file),
+        };
+        let failed = result.is_err();
+        outcomes.push((file, result));
+        if failed && !batch.keep_going {
+            break;
+        }
+    }
+
+    let mut failures = 0;
+    for (file, result) in &outcomes {
+        match result {
+            Ok(()) => println!("{}: ok", file),
+            Err(e) => {
+                failures += 1;
+                println!("{}: failed ({})", file, e);
+            }
+        }
+    }
+    println!(
+        "{} succeeded, {} failed, {} not processed",
+        outcomes.len() - failures,
+        failures,
+        files.len() - outcomes.len()
+    );
+    if !batch.keep_going {
+        if let Some((_, Err(_))) = outcomes.last() {
+            // Hand back the original error so the exit code still reflects its kind.
+            return outcomes.pop().unwrap().1;
+        }
+    }
+    if failures > 0 {
+        return Err(format!("{} of {} files failed", failures, files.len()).into());
+    }
+    Ok(())
+}
+
+/// Copies `file` into `dir` under its own name, refusing stdin and name clashes.
+fn batch_output(
+    file: &str,
+    dir: &str,
+    used_names: &mut Vec<std::ffi::OsString>,
+) -> Result<String, Box<dyn Error>> {
+    if file == STDIO_PATH {
+        return Err("stdin can't be used with --output-dir".into());
+    }
+    let name = Path::new(file)
+        .file_name()
+        .ok_or_else(|| format!("{} has no file name", file))?
+        .to_owned();
+    if used_names.contains(&name) {
+        return Err(format!("another input is also named {}", name.to_string_lossy()).into());
+    }
+    fs::create_dir_all(dir)?;
+    let target = Path::new(dir).join(&name);
+    fs::copy(file, &target)?;
+    used_names.push(name);
+    Ok(target.to_string_lossy().into_owned())
+}
+
+/// Ends an `encode` that has nothing to do, saying why on stderr.
+fn skip_encode(reason: &str, input: &[u8], options: &EncodeOptions) -> Result<(), Box<dyn Error>> {
+    info!("Skipped: {}", reason);
+    // Keep pipelines flowing by passing the input through untouched.
+    if options.stdout {
+        write_file(STDIO_PATH, input, options.io)?;
+    }
+    Ok(())
+}
+
+/// Whether `image` already stores the payload in `new_chunks`, judged by the CRC each
+/// chunk's type and data hash to, so no separate hash chunk has to be stored. A single
+/// chunk matches any existing chunk of its type; split parts must match the existing
+/// parts of the type one for one, in order.
+fn holds_payload(image: &Png, new_chunks: &[Chunk]) -> bool {
+    let fingerprint = |c: &Chunk| (c.length(), c.expected_crc());
+    let chunk_type = match new_chunks.first() {
+        Some(chunk) => chunk.chunk_type(),
+        None => return false,
+    };
+    let existing: Vec<(u32, u32)> = image
+        .chunks()
+        .iter()
+        .filter(|c| c.chunk_type() == chunk_type)
+        .map(fingerprint)
+        .collect();
+    match new_chunks {
+        [chunk] => existing.contains(&fingerprint(chunk)),
+        parts => existing == parts.iter().map(fingerprint).collect::<Vec<_>>(),
+    }
+}
+
+/// Reads a CRC given as a decimal number or as `0x` and hex digits, the two forms
+/// `list` prints.
+pub fn parse_crc(value: &str) -> Result<u32, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("{:?} is not a CRC (try 0xAE426082 or 2923585666)", value))
+}
+
+/// Reads a chunk type written as 8 hex digits (`72755374` is `ruSt`). Bytes that
+/// aren't a valid type are accepted with a warning, since producing such files is the
+/// point of `--type-hex`. pngme's own parser rejects such files, so they're only for
+/// testing other tools.
+fn parse_type_hex(hex: &str) -> Result<ChunkType, Box<dyn Error>> {
+    if hex.len() != 8 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("--type-hex expects 8 hex digits, found {:?}", hex).into());
+    }
+    let mut bytes = [0u8; 4];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    match ChunkType::try_from(bytes) {
+        Ok(chunk_type) if chunk_type.is_valid() => Ok(chunk_type),
+        _ => {
+            warn!(
+                "type bytes {} are not a valid chunk type; the file will violate the PNG spec \
+                 and pngme will not be able to read it back",
+                hex
+            );
+            Ok(ChunkType::from_bytes_unchecked(bytes))
+        }
+    }
+}
+
+/// Hidden data belongs in a private ancillary type such as `ruSt`. Critical types make
+/// decoders that don't recognize them reject the file, and standard types get
+/// interpreted by every viewer.
+fn warn_if_risky_type(chunk_type: &ChunkType) {
+    if chunk_type.is_critical() {
+        warn!(
+            "{} is a critical chunk type; decoders that don't know it will reject \
+             the image (use --force to silence)",
+            chunk_type
+        );
+    } else if chunk_type.is_standard() {
+        warn!(
+            "{} is a standard chunk type that other tools will try to interpret \
+             (use --force to silence)",
+            chunk_type
+        );
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DecodeOptions {
+    /// Reassemble a payload written by `encode --split`.
+    pub split: bool,
+    /// Decode the text with this charset. `None` uses the chunk's own: Latin-1 for `tEXt`
+    /// and `zTXt`, as the PNG spec says, and UTF-8 for everything else.
+    pub charset: Option<Charset>,
+    /// Read chunks even if their CRC is wrong, warning about each one.
+    pub no_crc_check: bool,
+    /// Strip padding added by `encode --pad-to`.
+    pub unpad: bool,
+    pub output_format: OutputFormat,
+    /// End pretty output with a NUL byte instead of a newline, for C-string consumers.
+    pub null_terminated: bool,
+    /// Where the PNG starts within the file; see [`ParseOptions::offset`].
+    pub offset: usize,
+    /// Show control characters other than newline and tab as `.`, so a crafted chunk
+    /// can't send escape sequences to the terminal. Raw output is never changed.
+    pub only_printable: bool,
+    /// Fail unless the chunk's stored CRC is this value, so a script knows it read the
+    /// exact chunk it meant to. Not available with `split`.
+    pub expect_crc: Option<u32>,
+    /// Print the payload once per representation, each on a labeled line, instead of
+    /// using `output_format`. Empty means the normal single output.
+    pub show: Vec<Representation>,
+    /// How the file is read (and written back, where the command does).
+    pub io: IoOptions,
+}
+
+/// A way `decode --show` can print the payload's bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representation {
+    Utf8,
+    Hex,
+    Base64,
+}
+
+impl Representation {
+    fn label(self) -> &'static str {
+        match self {
+            Representation::Utf8 => "utf8",
+            Representation::Hex => "hex",
+            Representation::Base64 => "base64",
+        }
+    }
+
+    fn render(self, data: &[u8]) -> String {
+        match self {
+            Representation::Utf8 => match std::str::from_utf8(data) {
+                Ok(text) => text.to_string(),
+                Err(e) => format!("<not valid UTF-8: {}>", e),
+            },
+            Representation::Hex => to_hex(data),
+            Representation::Base64 => to_base64(data),
+        }
+    }
+}
+
+impl FromStr for Representation {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Representation::Utf8),
+            "hex" => Ok(Representation::Hex),
+            "base64" => Ok(Representation::Base64),
+            other => Err(format!(
+                "unknown representation {} (expected utf8, hex or base64)",
+                other
+            )),
+        }
+    }
+}
+
+/// How `decode` prints the payload.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Decode as text with the chosen charset and add a newline, for terminals.
+    #[default]
+    Pretty,
+    /// Write the payload bytes exactly as stored, for piping binary data.
+    Raw,
+    /// Print the payload as lowercase hex digits on one line.
+    Hex,
+    /// Like `Pretty` when the payload decodes with the chosen charset, otherwise `Hex`
+    /// with a warning, so any chunk can be decoded without guessing its encoding.
+    Auto,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" => Ok(OutputFormat::Pretty),
+            "raw" => Ok(OutputFormat::Raw),
+            "hex" => Ok(OutputFormat::Hex),
+            "auto" => Ok(OutputFormat::Auto),
+            other => Err(format!(
+                "unknown output format {} (expected raw, hex, pretty or auto)",
+                other
+            )),
+        }
+    }
+}
+
+pub fn decode(
+    file_path: &str,
+    chunk_type: &str,
+    options: &DecodeOptions,
+) -> Result<(), Box<dyn Error>> {
+    if options.null_terminated && options.output_format == OutputFormat::Raw {
+        return Err("--null-terminated cannot be combined with --output-format raw".into());
+    }
+    if options.split && options.expect_crc.is_some() {
+        return Err("--expect-crc cannot be combined with --split".into());
+    }
+    let image = read_png(file_path, options.no_crc_check, options.offset, options.io)?;
+    let message = if options.split {
+        let parts = image.chunks_by_type(chunk_type);
+        if parts.is_empty() {
+            return Err(ChunkNotFound::new(chunk_type).into());
+        }
+        join_payload(&parts)?
+    } else {
+        let chunk = image
+            .chunk_by_type(chunk_type)
+            .ok_or_else(|| ChunkNotFound::new(chunk_type))?;
+        match options.expect_crc {
+            Some(expected) if chunk.crc() != expected => {
+                return Err(format!(
+                    "expected CRC 0x{:08X} ({}), but the {} chunk has 0x{:08X} ({})",
+                    expected,
+                    expected,
+                    chunk_type,
+                    chunk.crc(),
+                    chunk.crc()
+                )
+                .into());
+            }
+            _ => chunk.data().to_vec(),
+        }
+    };
+    let message = if options.unpad {
+        unpad_payload(&message)?.to_vec()
+    } else {
+        message
+    };
+    if !options.show.is_empty() {
+        let width = options
+            .show
+            .iter()
+            .map(|r| r.label().len())
+            .max()
+            .unwrap_or(0);
+        for representation in &options.show {
+            let mut value = representation.render(&message);
+            if options.only_printable {
+                value = printable_only(&value);
+            }
+            println!(
+                "{:<width$}  {}",
+                representation.label(),
+                value,
+                width = width
+            );
+        }
+        return Ok(());
+    }
+    if options.output_format == OutputFormat::Raw {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&message)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    let keyword_text = match chunk_type {
+        _ if options.split => None,
+        "tEXt" => {
+            let TextChunk { keyword, text } = TextChunk::try_from(message.as_slice())?;
+            Some((keyword, text))
+        }
+        "zTXt" => {
+            let CompressedTextChunk { keyword, text } =
+                CompressedTextChunk::try_from(message.as_slice())?;
+            Some((keyword, text))
+        }
+        _ => None,
+    };
+    let decoded = match keyword_text {
+        Some((keyword, text)) => options
+            .charset
+            .unwrap_or(Charset::Latin1)
+            .decode(&text)
+            .map(|decoded| format!("{}: {}", keyword, decoded)),
+        None => options.charset.unwrap_or_default().decode(&message),
+    };
+    let text = match (options.output_format, decoded) {
+        (OutputFormat::Hex, _) => to_hex(&message),
+        (OutputFormat::Auto, Err(e)) => {
+            warn!("payload doesn't decode as text ({}), showing it as hex", e);
+            to_hex(&message)
+        }
+        (_, decoded) => decoded?,
+    };
+    let text = if options.only_printable {
+        printable_only(&text)
+    } else {
+        text
+    };
+    let terminator = if options.null_terminated {
+        b"\0"
+    } else {
+        b"\n"
+    };
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.write_all(terminator)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+pub fn remove(
+    file_path: &str,
+    chunk_type: &str,
+    force: bool,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    let parsed_type = ChunkType::from_str(chunk_type)?;
+    let mut image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    if image.chunk_by_type(chunk_type).is_none() {
+        return Err(ChunkNotFound::new(chunk_type).into());
+    }
+    check_removable(&parsed_type, force)?;
+    image.remove_first_chunk(chunk_type)?;
+    write_file(file_path, &image.as_bytes(), io_options)?;
+    Ok(())
+}
+
+/// Removes the chunk at position `index`, with the same critical-chunk check as [`remove`].
+pub fn remove_at(
+    file_path: &str,
+    index: usize,
+    force: bool,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    if let Some(chunk) = image.chunks().get(index) {
+        check_removable(chunk.chunk_type(), force)?;
+    }
+    image.remove_chunk_at(index)?;
+    write_file(file_path, &image.as_bytes(), io_options)?;
+    Ok(())
+}
+
+/// Which chunks `remove_matching` drops, by their property bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoveFilter {
+    /// Every ancillary chunk: metadata and any hidden payloads.
+    Ancillary,
+    /// Every chunk whose safe-to-copy bit is clear, i.e. ones editors are told to drop
+    /// when they change the image.
+    UnsafeToCopy,
+}
+
+impl RemoveFilter {
+    fn matches(self, chunk_type: &ChunkType) -> bool {
+        match self {
+            RemoveFilter::Ancillary => !chunk_type.is_critical(),
+            RemoveFilter::UnsafeToCopy => !chunk_type.is_safe_to_copy(),
+        }
+    }
+}
+
+/// Removes every chunk `filter` selects and reports how many went. Critical chunks are
+/// never safe to copy, so `UnsafeToCopy` would select them too; they're kept unless
+/// `force` is set.
+pub fn remove_matching(
+    file_path: &str,
+    filter: RemoveFilter,
+    force: bool,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    let mut image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let mut kept_critical = 0;
+    let removed = image.remove_where(|chunk| {
+        let chunk_type = chunk.chunk_type();
+        if !filter.matches(chunk_type) {
+            return false;
+        }
+        if chunk_type.is_critical() && !force {
+            kept_critical += 1;
+            return false;
+        }
+        true
+    });
+    for chunk in removed.iter().filter(|c| c.chunk_type().is_critical()) {
+        check_removable(chunk.chunk_type(), force)?;
+    }
+    write_file(file_path, &image.as_bytes(), io_options)?;
+    println!("Removed {} chunks", removed.len());
+    if kept_critical > 0 {
+        println!(
+            "Kept {} critical chunks (pass --force to remove them too)",
+            kept_critical
+        );
+    }
+    Ok(())
+}
+
+/// Critical chunks carry the image itself, so dropping one almost always leaves a file
+/// decoders reject. Refuse unless `force` is set, and warn when it is.
+fn check_removable(chunk_type: &ChunkType, force: bool) -> Result<(), Box<dyn Error>> {
+    if !chunk_type.is_critical() {
+        return Ok(());
+    }
+    if !force {
+        return Err(format!(
+            "{} is a critical chunk and removing it will likely make the image unreadable; \
+             pass --force to remove it anyway",
+            chunk_type
+        )
+        .into());
+    }
+    warn!(
+        "removing critical chunk {}; the image may no longer decode",
+        chunk_type
+    );
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct PrintOptions {
+    /// Follow each chunk with a hex dump of its data.
+    pub hexdump: bool,
+    /// Dump all of the data rather than the first `HEXDUMP_PREVIEW_LEN` bytes.
+    pub full: bool,
+    /// Color critical chunks cyan, ancillary chunks yellow and bad CRCs red.
+    pub color: bool,
+    /// Read chunks even if their CRC is wrong, warning about each one.
+    pub no_crc_check: bool,
+    /// Print at most this many chunks; 0 means no limit.
+    pub limit: usize,
+    /// Show CRCs in decimal rather than hex.
+    pub crc_dec: bool,
+    /// Where the PNG starts within the file; see [`ParseOptions::offset`].
+    pub offset: usize,
+    /// Follow each chunk with a line saying what its type is for.
+    pub describe: bool,
+    /// The order chunks are listed in; `limit` applies after sorting.
+    pub sort: SortKey,
+    /// List in the opposite order, e.g. largest chunk first with `SortKey::Length`.
+    pub reverse: bool,
+    /// How the file is read (and written back, where the command does).
+    pub io: IoOptions,
+}
+
+/// What `list --sort` orders chunks by. Ties keep file order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Position in the file.
+    #[default]
+    Offset,
+    /// Chunk type, compared byte by byte.
+    Type,
+    /// Data length, smallest first.
+    Length,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "offset" => Ok(SortKey::Offset),
+            "type" => Ok(SortKey::Type),
+            "length" => Ok(SortKey::Length),
+            other => Err(format!(
+                "unknown sort key {} (expected offset, type or length)",
+                other
+            )),
+        }
+    }
+}
+
+/// `chunks` in the order `list` shows them.
+fn sorted_chunks(chunks: &[Chunk], sort: SortKey, reverse: bool) -> Vec<&Chunk> {
+    let mut sorted: Vec<&Chunk> = chunks.iter().collect();
+    match sort {
+        SortKey::Offset => {}
+        SortKey::Type => sorted.sort_by(|a, b| a.chunk_type().cmp(b.chunk_type())),
+        SortKey::Length => sorted.sort_by_key(|chunk| chunk.length()),
+    }
+    if reverse {
+        sorted.reverse();
+    }
+    sorted
+}
+
+pub fn print_chunks(file_path: &str, options: &PrintOptions) -> Result<(), Box<dyn Error>> {
+    let image = read_png(file_path, options.no_crc_check, options.offset, options.io)?;
+    let chunks = sorted_chunks(image.chunks(), options.sort, options.reverse);
+    let shown = match options.limit {
+        0 => chunks.len(),
+        limit => limit.min(chunks.len()),
+    };
+    for chunk in &chunks[..shown] {
+        let text = if options.crc_dec {
+            format!("{:#}", chunk)
+        } else {
+            chunk.to_string()
+        };
+        if options.color {
+            let color = if !chunk.crc_is_valid() {
+                Color::Red
+            } else if chunk.chunk_type().is_critical() {
+                Color::Cyan
+            } else {
+                Color::Yellow
+            };
+            println!("{}", paint(&text, color));
+        } else {
+            println!("{}", text);
+        }
+        if options.describe {
+            println!("{}\n", chunk.type_name_description());
+        }
+        if !options.hexdump {
+            continue;
+        }
+        let data = chunk.data();
+        let shown = if options.full {
+            data.len()
+        } else {
+            data.len().min(HEXDUMP_PREVIEW_LEN)
+        };
+        print!("{}", hexdump(&data[..shown]));
+        if shown < data.len() {
+            println!("... {} more bytes (use --full)", data.len() - shown);
+        }
+        println!();
+    }
+    if shown < chunks.len() {
+        println!("... and {} more", chunks.len() - shown);
+    }
+    Ok(())
+}
+
+/// How often `watch` checks the file's modification time.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Prints the chunk list, then reprints it every time the file's modification time
+/// changes. Polls rather than relying on filesystem notifications; runs until the
+/// process is interrupted.
+pub fn watch(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    // Only a file missing at startup is an error. Editors often save by renaming a new
+    // file into place, so later it can briefly vanish; that just isn't a change yet.
+    fs::metadata(file_path)?.modified()?;
+    let mut last_modified = None;
+    loop {
+        let modified = match fs::metadata(file_path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(_) => {
+                thread::sleep(WATCH_POLL_INTERVAL);
+                continue;
+            }
+        };
+        if last_modified != Some(modified) {
+            last_modified = Some(modified);
+            // A file caught halfway through being rewritten may not parse yet; report
+            // it and wait for the next change instead of giving up.
+            if let Err(e) = print_chunks(
+                file_path,
+                &PrintOptions {
+                    io: io_options,
+                    ..PrintOptions::default()
+                },
+            ) {
+                error!("{}", e);
+            }
+            println!("--- watching {} (Ctrl-C to stop) ---", file_path);
+        }
+        thread::sleep(WATCH_POLL_INTERVAL);
+    }
+}
+
+/// Replaces every control character except newline and tab with `.`.
+fn printable_only(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() && c != '\n' && c != '\t' {
+                '.'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Formats `data` as one run of lowercase hex digits, two per byte.
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Encodes `data` as standard padded base64 (RFC 4648).
+fn to_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bits = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        // A group of n bytes yields n + 1 base64 digits; `=` pads it to four.
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Formats `data` as 16-byte rows of offset, hex bytes and printable ASCII.
+fn hexdump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (row, bytes) in data.chunks(16).enumerate() {
+        let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        out.push_str(&format!(
+            "{:08x}  {:<47}  |{}|\n",
+            row * 16,
+            hex.join(" "),
+            ascii
+        ));
+    }
+    out
+}
+
+/// Writes the data of the first chunk of `chunk_type` to `output`. With `all`, every
+/// matching chunk is written to its own numbered file instead (`out.0.bin`, `out.1.bin`, ...).
+/// With `split`, the chunks are reassembled as `decode --split` does and written as one file.
+pub fn extract(
+    file_path: &str,
+    chunk_type: &str,
+    output: &str,
+    all: bool,
+    split: bool,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let chunks = image.chunks_by_type(chunk_type);
+    if chunks.is_empty() {
+        return Err(ChunkNotFound::new(chunk_type).into());
+    }
+    if split {
+        let payload = join_payload(&chunks)?;
+        fs::write(output, &payload)?;
+        println!(
+            "Wrote {} bytes from {} chunks to {}",
+            payload.len(),
+            chunks.len(),
+            output
+        );
+        return Ok(());
+    }
+    if !all {
+        fs::write(output, chunks[0].data())?;
+        println!("Wrote {} bytes to {}", chunks[0].data().len(), output);
+        return Ok(());
+    }
+    for (i, chunk) in chunks.iter().enumerate() {
+        fs::write(numbered_path(output, i), chunk.data())?;
+    }
+    println!("Wrote {} files", chunks.len());
+    Ok(())
+}
+
+/// Writes a `width` x `height` grayscale PNG of one solid `level` to `out_file`, so
+/// there's a file to try the other commands on.
+pub fn new_image(
+    out_file: &str,
+    width: u32,
+    height: u32,
+    level: u8,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    let image = Png::solid_gray(width, height, level)?;
+    write_file(out_file, &image.as_bytes(), io_options)?;
+    if out_file != STDIO_PATH {
+        println!("Wrote {}x{} image to {}", width, height, out_file);
+    }
+    Ok(())
+}
+
+/// Name of the file `dump` writes next to the chunk files, one line per chunk.
+const MANIFEST_NAME: &str = "manifest.txt";
+
+/// Writes every chunk's data to `out_dir` as `<index>_<type>.bin` (e.g. `0002_ruSt.bin`),
+/// plus a manifest listing each file's type, length and CRC.
+pub fn dump(file_path: &str, out_dir: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let out_dir = Path::new(out_dir);
+    fs::create_dir_all(out_dir)?;
+    let mut manifest = String::from("# file type length crc\n");
+    for (index, chunk) in image.chunks().iter().enumerate() {
+        let name = format!("{:04}_{}.bin", index, chunk.chunk_type());
+        fs::write(out_dir.join(&name), chunk.data())?;
+        manifest.push_str(&format!(
+            "{} {} {} {:08x}\n",
+            name,
+            chunk.chunk_type(),
+            chunk.length(),
+            chunk.crc()
+        ));
+    }
+    fs::write(out_dir.join(MANIFEST_NAME), manifest)?;
+    println!(
+        "Wrote {} chunks to {}",
+        image.chunks().len(),
+        out_dir.display()
+    );
+    Ok(())
+}
+
+/// Rebuilds a PNG from a directory written by `dump`, taking chunks in file name order
+/// and their types from the manifest. Lengths and CRCs are recomputed, so the data files
+/// can be edited freely; the manifest's own length and CRC columns are ignored.
+pub fn assemble(in_dir: &str, out_file: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let in_dir = Path::new(in_dir);
+    let manifest = fs::read_to_string(in_dir.join(MANIFEST_NAME))?;
+    let mut entries = parse_manifest(&manifest)?;
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut chunks = Vec::with_capacity(entries.len());
+    for (name, chunk_type) in entries {
+        let data = fs::read(in_dir.join(&name))?;
+        chunks.push(Chunk::try_new(chunk_type, data)?);
+    }
+    let image = Png::from_chunks(chunks);
+    if let Err(errors) = image.validate_order() {
+        for error in &errors {
+            match error {
+                OrderError::IhdrNotFirst | OrderError::IendNotLast => {
+                    return Err(format!("refusing to write {}: {}", out_file, error).into());
+                }
+                _ => warn!("{}", error),
+            }
+        }
+    }
+    write_file(out_file, &image.as_bytes(), io_options)?;
+    println!("Wrote {} chunks to {}", image.chunks().len(), out_file);
+    Ok(())
+}
+
+/// Reads `(file name, chunk type)` pairs from a manifest, skipping blank and `#` lines.
+fn parse_manifest(manifest: &str) -> Result<Vec<(String, ChunkType)>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for (line_number, line) in manifest.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next()) {
+            (Some(name), Some(chunk_type)) => {
+                entries.push((name.to_string(), ChunkType::from_str(chunk_type)?))
+            }
+            _ => {
+                return Err(format!(
+                    "manifest line {}: expected a file name and a chunk type",
+                    line_number + 1
+                )
+                .into())
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Inserts `index` before the extension of `path`, so `out.bin` becomes `out.3.bin`.
+fn numbered_path(path: &str, index: usize) -> PathBuf {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, index),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Retypes the first chunk of type `from` in place, keeping its data and position.
+pub fn rename_type(
+    file_path: &str,
+    from: &str,
+    to: &str,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    ChunkType::from_str(from)?;
+    let new_type = ChunkType::from_str(to)?;
+    if !new_type.is_valid() {
+        return Err(format!("{} is not a valid chunk type", to).into());
+    }
+    let mut image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    image
+        .chunk_by_type_mut(from)
+        .ok_or_else(|| ChunkNotFound::new(from))?
+        .set_chunk_type(new_type);
+    write_file(file_path, &image.as_bytes(), io_options)?;
+    Ok(())
+}
+
+/// Appends the first chunk of `chunk_type` in `src` to `dst`, byte for byte (CRC
+/// included), keeping `IEND` last.
+pub fn copy_chunk(
+    src: &str,
+    dst: &str,
+    chunk_type: &str,
+    io_options: IoOptions,
+) -> Result<(), Box<dyn Error>> {
+    ChunkType::from_str(chunk_type)?;
+    let source = Png::try_from(read_file(src, io_options)?.as_slice())?;
+    let chunk = source
         .chunk_by_type(chunk_type)
-        .expect("incorrect chunk type");
-    println!("{}", Chunk::data_as_string(encoded_chunk).unwrap());
+        .ok_or_else(|| ChunkNotFound::new(chunk_type))?;
+    let mut image = Png::try_from(read_file(dst, io_options)?.as_slice())?;
+    image.append_chunk(chunk.clone());
+    image.finalize()?;
+    write_file(dst, &image.as_bytes(), io_options)?;
+    Ok(())
+}
+
+pub fn capacity(file_path: &str) -> Result<(), Box<dyn Error>> {
+    let file_size = fs::metadata(file_path)?.len();
+    println!("File size: {} bytes", file_size);
+    println!("Max data per chunk: {} bytes (u32::MAX)", u32::MAX);
+    println!("Larger payloads must be split across multiple chunks");
+    Ok(())
+}
+
+/// Prints the CRC-32 of the whole file, in the `sha256sum` layout. Any file is
+/// accepted, so it also works on outputs that no longer parse as PNGs.
+pub fn checksum(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let bytes = read_file(file_path, io_options)?;
+    println!("{:08x}  {}", crc::crc32::checksum_ieee(&bytes), file_path);
+    Ok(())
+}
+
+/// Prints how many chunks of each type the file has and how many data bytes each type
+/// holds, most common first, so unexpected chunks stand out.
+pub fn stats(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let counts = sorted_by_count(image.chunk_counts_by_type());
+    let histogram: Vec<String> = counts
+        .iter()
+        .map(|(chunk_type, n)| format!("{}: {}", chunk_type, n))
+        .collect();
+    println!("Chunk types: {}", histogram.join(", "));
+    println!("Data bytes by type:");
+    for (chunk_type, bytes) in sorted_by_count(image.data_bytes_by_type()) {
+        println!("  {}: {}", chunk_type, bytes);
+    }
+    Ok(())
+}
+
+/// Largest value first; ties go alphabetically so the output is stable.
+fn sorted_by_count(map: HashMap<ChunkType, usize>) -> Vec<(ChunkType, usize)> {
+    let mut entries: Vec<(ChunkType, usize)> = map.into_iter().collect();
+    entries.sort_by(|(a_type, a), (b_type, b)| b.cmp(a).then_with(|| a_type.cmp(b_type)));
+    entries
+}
+
+/// Lists every offset where a PNG signature begins and whether a PNG parses from
+/// there. Each candidate is parsed up to its first `IEND`, so PNGs concatenated back to
+/// back are reported separately and a PNG stored inside another one's chunk is reported
+/// along with its container.
+pub fn scan(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let bytes = read_file(file_path, io_options)?;
+    let offsets = Png::find_signatures(&bytes);
+    if offsets.is_empty() {
+        println!("No PNG signature found");
+        return Ok(());
+    }
+    for offset in offsets {
+        let options = ParseOptions {
+            offset,
+            stop_at_iend: true,
+            ..ParseOptions::default()
+        };
+        match Png::try_from_with_options(&bytes, &options) {
+            Ok(image) => println!(
+                "offset {}: valid PNG, {} chunks, {} bytes",
+                offset,
+                image.chunk_count(),
+                image.as_bytes().len()
+            ),
+            Err(e) => println!("offset {}: does not parse ({})", offset, e),
+        }
+    }
+    Ok(())
+}
+
+/// Prints `Png::fingerprint`, which changes when chunks are added, removed, reordered
+/// or resized but not when only their contents change.
+pub fn fingerprint(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    println!("{:08x}", image.fingerprint());
+    Ok(())
+}
+
+/// How one chunk of a file compares to the other file in `diff`. Indices are chunk
+/// positions within each file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ChunkDiff {
+    Same {
+        chunk_type: String,
+        a: usize,
+        b: usize,
+    },
+    Differs {
+        chunk_type: String,
+        a: usize,
+        b: usize,
+    },
+    OnlyInA {
+        chunk_type: String,
+        a: usize,
+    },
+    OnlyInB {
+        chunk_type: String,
+        b: usize,
+    },
+}
+
+impl fmt::Display for ChunkDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Same { chunk_type, a, b } => {
+                write!(f, "same     {}  a#{} b#{}", chunk_type, a, b)
+            }
+            Self::Differs { chunk_type, a, b } => {
+                write!(f, "differs  {}  a#{} b#{}", chunk_type, a, b)
+            }
+            Self::OnlyInA { chunk_type, a } => write!(f, "only-a   {}  a#{}", chunk_type, a),
+            Self::OnlyInB { chunk_type, b } => write!(f, "only-b   {}  b#{}", chunk_type, b),
+        }
+    }
+}
+
+/// Pairs the n-th chunk of each type in `a` with the n-th chunk of that type in `b`, so
+/// an inserted or removed chunk shows up on its own instead of shifting every later
+/// comparison. Results follow `a`'s order, then any chunks only found in `b`.
+pub fn diff_chunks(a: &[Chunk], b: &[Chunk]) -> Vec<ChunkDiff> {
+    let mut matched_in_b = vec![false; b.len()];
+    let mut diffs = Vec::new();
+    for (a_index, a_chunk) in a.iter().enumerate() {
+        let chunk_type = a_chunk.chunk_type().to_string();
+        let partner = b.iter().enumerate().position(|(b_index, b_chunk)| {
+            !matched_in_b[b_index] && b_chunk.chunk_type() == a_chunk.chunk_type()
+        });
+        match partner {
+            Some(b_index) => {
+                matched_in_b[b_index] = true;
+                diffs.push(if *a_chunk == b[b_index] {
+                    ChunkDiff::Same {
+                        chunk_type,
+                        a: a_index,
+                        b: b_index,
+                    }
+                } else {
+                    ChunkDiff::Differs {
+                        chunk_type,
+                        a: a_index,
+                        b: b_index,
+                    }
+                });
+            }
+            None => diffs.push(ChunkDiff::OnlyInA {
+                chunk_type,
+                a: a_index,
+            }),
+        }
+    }
+    for (b_index, b_chunk) in b.iter().enumerate() {
+        if !matched_in_b[b_index] {
+            diffs.push(ChunkDiff::OnlyInB {
+                chunk_type: b_chunk.chunk_type().to_string(),
+                b: b_index,
+            });
+        }
+    }
+    diffs
+}
+
+pub fn diff(file_a: &str, file_b: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image_a = Png::try_from(read_file(file_a, io_options)?.as_slice())?;
+    let image_b = Png::try_from(read_file(file_b, io_options)?.as_slice())?;
+    for chunk_diff in diff_chunks(image_a.chunks(), image_b.chunks()) {
+        println!("{}", chunk_diff);
+    }
+    Ok(())
+}
+
+/// Prints the keyword and value of every textual metadata chunk as a table.
+pub fn list_keywords(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let rows = keyword_rows(image.chunks());
+    if rows.is_empty() {
+        println!("No text chunks");
+        return Ok(());
+    }
+    let width = rows
+        .iter()
+        .map(|(k, _)| k.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (keyword, value) in rows {
+        println!("{:<width$}  {}", keyword, value, width = width);
+    }
+    Ok(())
+}
+
+/// One `(keyword, value)` row per `tEXt`, `zTXt` or `iTXt` chunk. Chunks that don't
+/// follow the keyword format are flagged in the value column instead of failing the
+/// whole listing. International text is not decoded yet.
+fn keyword_rows(chunks: &[Chunk]) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+    for chunk in chunks {
+        let chunk_type = match chunk.chunk_type_str() {
+            Some(chunk_type @ ("tEXt" | "zTXt" | "iTXt")) => chunk_type,
+            _ => continue,
+        };
+        let row = match TextChunk::try_from(chunk.data()) {
+            Ok(text) if chunk_type == "tEXt" => {
+                let value = Charset::Latin1
+                    .decode(&text.text)
+                    .unwrap_or_else(|e| format!("<{}>", e));
+                (text.keyword, value)
+            }
+            Ok(text) if chunk_type == "zTXt" => {
+                let value = CompressedTextChunk::try_from(chunk.data())
+                    .and_then(|text| Charset::Latin1.decode(&text.text))
+                    .unwrap_or_else(|e| format!("<{}>", e));
+                (text.keyword, value)
+            }
+            Ok(text) => (text.keyword, format!("<{} value not decoded>", chunk_type)),
+            Err(e) => ("?".to_string(), format!("<{}>", e)),
+        };
+        rows.push(row);
+    }
+    rows
+}
+
+#[derive(Debug, Default)]
+pub struct FindOptions {
+    /// Print only the number of matches per chunk type.
+    pub count: bool,
+    /// Only search chunks of this type.
+    pub chunk_type: Option<String>,
+    /// Compare `chunk_type` without regard to case. The search itself stays exact.
+    pub ignore_type_case: bool,
+    /// How the file is read (and written back, where the command does).
+    pub io: IoOptions,
+}
+
+/// Searches every chunk's data for `needle`. Prints each location by default, or with
+/// `count` just the number of matches per chunk type, which stays readable on files
+/// with thousands of IDAT chunks.
+pub fn find(file_path: &str, needle: &str, options: &FindOptions) -> Result<(), Box<dyn Error>> {
+    if needle.is_empty() {
+        return Err("find needs a non-empty search string".into());
+    }
+    let image = Png::try_from(read_file(file_path, options.io)?.as_slice())?;
+    let wanted = |chunk: &Chunk| match &options.chunk_type {
+        Some(t) if options.ignore_type_case => chunk.chunk_type().matches_ignore_case(t),
+        Some(t) => chunk.chunk_type().matches(t),
+        None => true,
+    };
+    let matches = find_matches(image.chunks(), needle.as_bytes(), wanted);
+    if matches.is_empty() {
+        println!("No matches");
+        return Ok(());
+    }
+    if !options.count {
+        for (index, offset) in matches {
+            let chunk_type = image.chunks()[index].chunk_type();
+            println!("{} at index {}, offset {}", chunk_type, index, offset);
+        }
+        return Ok(());
+    }
+    for (chunk_type, n) in count_by_type(image.chunks(), &matches) {
+        println!("{}: {}", chunk_type, n);
+    }
+    Ok(())
+}
+
+/// `(chunk index, data offset)` of every occurrence of `needle` in the chunks `wanted`
+/// accepts, overlapping ones included.
+fn find_matches(
+    chunks: &[Chunk],
+    needle: &[u8],
+    wanted: impl Fn(&Chunk) -> bool,
+) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate().filter(|(_, c)| wanted(c)) {
+        for (offset, window) in chunk.data().windows(needle.len()).enumerate() {
+            if window == needle {
+                matches.push((index, offset));
+            }
+        }
+    }
+    matches
 }
 
-pub fn remove(file_path: &str, chunk_type: &str) {
-    let mut image = Png::try_from(fs::read(file_path).unwrap().as_slice()).unwrap();
-    image.remove_first_chunk(chunk_type);
-    fs::write(file_path, image.as_bytes()).unwrap();
+/// Match counts per chunk type, in the order each type first matched.
+fn count_by_type<'a>(
+    chunks: &'a [Chunk],
+    matches: &[(usize, usize)],
+) -> Vec<(&'a ChunkType, usize)> {
+    let mut counts: Vec<(&ChunkType, usize)> = Vec::new();
+    for &(index, _) in matches {
+        let chunk_type = chunks[index].chunk_type();
+        match counts.iter_mut().find(|(t, _)| *t == chunk_type) {
+            Some((_, n)) => *n += 1,
+            None => counts.push((chunk_type, 1)),
+        }
+    }
+    counts
 }
 
-pub fn print_chunks(file_path: &str) {
-    let mut image = Png::try_from(fs::read(file_path).unwrap().as_slice()).unwrap();
-    println!("{}", String::from_utf8(image.as_bytes()).unwrap());
+/// Checks chunk ordering and critical chunk lengths, printing every problem found.
+/// Fails with a parse error (exit code 2) if there were any. CRCs are checked while
+/// reading. With `color`, the OK line is printed green and each problem red.
+pub fn verify(file_path: &str, color: bool, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let mut problems: Vec<String> = Vec::new();
+    if let Err(errors) = image.validate_order() {
+        problems.extend(errors.iter().map(|e| e.to_string()));
+    }
+    if let Err(errors) = image.validate_lengths() {
+        problems.extend(errors.iter().map(|e| e.to_string()));
+    }
+    let paint_if = |text: &str, c: Color| {
+        if color {
+            paint(text, c)
+        } else {
+            text.to_string()
+        }
+    };
+    if problems.is_empty() {
+        let ok = format!("OK: {} chunks", image.chunk_count());
+        println!("{}", paint_if(&ok, Color::Green));
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{}", paint_if(problem, Color::Red));
+    }
+    Err(PngError::new(format!(
+        "{} problems found in {}",
+        problems.len(),
+        file_path
+    ))
+    .into())
+}
+
+/// Decodes the whole image, pixels included, with the `image` crate. Catches edits
+/// that leave the chunk structure valid but break the file for real decoders, which
+/// `verify` can't see.
+#[cfg(feature = "image-validate")]
+pub fn validate(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let bytes = read_file(file_path, io_options)?;
+    match image::load_from_memory_with_format(&bytes, image::ImageFormat::Png) {
+        Ok(decoded) => {
+            println!("OK: decoded {}x{} image", decoded.width(), decoded.height());
+            Ok(())
+        }
+        Err(e) => Err(PngError::new(format!("decoder rejected the image: {}", e)).into()),
+    }
+}
+
+pub fn info(file_path: &str, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    let image = Png::try_from(read_file(file_path, io_options)?.as_slice())?;
+    let header = image.image_header()?;
+    println!("Width: {}", header.width);
+    println!("Height: {}", header.height);
+    println!("Bit depth: {}", header.bit_depth);
+    println!("Color type: {}", header.color_type_name());
+    println!("Interlace: {}", header.interlace_name());
+    println!(
+        "Animated: {}",
+        if image.is_animated() { "yes" } else { "no" }
+    );
+    println!("Chunks: {}", image.chunk_count());
+    let mut seen: Vec<&ChunkType> = Vec::new();
+    for chunk in image.chunks() {
+        if !seen.contains(&chunk.chunk_type()) {
+            seen.push(chunk.chunk_type());
+            println!("  {}", chunk.type_name_description());
+        }
+    }
+    // A broken tIME shouldn't hide the rest of the report.
+    match image.last_modified() {
+        Ok(Some(time)) => println!("Last modified: {}", time),
+        Ok(None) => {}
+        Err(e) => println!("Last modified: invalid ({})", e),
+    }
+    print_color_info(&image);
+    Ok(())
+}
+
+/// The `info` lines for the color-management chunks, each omitted when its chunk is
+/// absent. Values are reported as stored; nothing is converted.
+fn print_color_info(image: &Png) {
+    if let Some(chunk) = image.chunk_by_type("sRGB") {
+        match Srgb::try_from(chunk) {
+            Ok(srgb) => println!(
+                "sRGB: yes, {} rendering intent ({})",
+                srgb.rendering_intent_name(),
+                srgb.rendering_intent
+            ),
+            Err(e) => println!("sRGB: invalid ({})", e),
+        }
+    }
+    if let Some(chunk) = image.chunk_by_type("gAMA") {
+        match Gamma::try_from(chunk) {
+            Ok(gamma) => println!("Gamma: {}", gamma),
+            Err(e) => println!("Gamma: invalid ({})", e),
+        }
+    }
+    if image.chunk_by_type("cHRM").is_some() {
+        println!("Chromaticities: present");
+    }
+    // iCCP starts with a profile name laid out like a tEXt keyword.
+    if let Some(chunk) = image.chunk_by_type("iCCP") {
+        match TextChunk::try_from(chunk.data()) {
+            Ok(profile) => println!("ICC profile: {}", profile.keyword),
+            Err(_) => println!("ICC profile: present (unnamed)"),
+        }
+    }
+}
+
+/// Appends `PADDING_MARKER` and then zeros until the length is a multiple of
+/// `multiple`. The marker is always added, so an already aligned payload grows by a
+/// full `multiple` bytes.
+fn pad_payload(mut data: Vec<u8>, multiple: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    if multiple == 0 {
+        return Err("padding multiple must be greater than zero".into());
+    }
+    data.push(PADDING_MARKER);
+    let remainder = data.len() % multiple;
+    if remainder != 0 {
+        data.resize(data.len() + multiple - remainder, 0);
+    }
+    Ok(data)
+}
+
+/// Strips trailing zeros and the `PADDING_MARKER` before them.
+fn unpad_payload(data: &[u8]) -> Result<&[u8], Box<dyn Error>> {
+    let end = data.iter().rposition(|&b| b != 0);
+    match end {
+        Some(end) if data[end] == PADDING_MARKER => Ok(&data[..end]),
+        _ => Err("data does not end with pngme padding".into()),
+    }
+}
+
+/// Divides `data` into parts of at most `part_size` bytes, each prefixed with its
+/// sequence header. An empty payload still produces a single (header-only) part.
+fn split_payload(data: &[u8], part_size: usize) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    if part_size == 0 {
+        return Err("split size must be greater than zero".into());
+    }
+    let parts: Vec<&[u8]> = if data.is_empty() {
+        vec![data]
+    } else {
+        data.chunks(part_size).collect()
+    };
+    let total = u32::try_from(parts.len())?;
+    Ok(parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            (i as u32)
+                .to_be_bytes()
+                .iter()
+                .chain(total.to_be_bytes().iter())
+                .chain(part.iter())
+                .copied()
+                .collect()
+        })
+        .collect())
+}
+
+/// Reassembles a payload written by `split_payload`, ordering the parts by their
+/// sequence header regardless of where they sit in the file.
+fn join_payload(chunks: &[&Chunk]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut parts: Vec<(u32, u32, &[u8])> = Vec::new();
+    for chunk in chunks {
+        let data = chunk.data();
+        if data.len() < SPLIT_HEADER_LEN {
+            return Err("split chunk is missing its sequence header".into());
+        }
+        let index = u32::from_be_bytes(data[0..4].try_into()?);
+        let total = u32::from_be_bytes(data[4..8].try_into()?);
+        parts.push((index, total, &data[SPLIT_HEADER_LEN..]));
+    }
+    parts.sort_by_key(|(index, _, _)| *index);
+
+    let total = match parts.first() {
+        Some((_, total, _)) => *total,
+        None => return Err("no chunks found to reassemble".into()),
+    };
+    if parts.len() != total as usize {
+        return Err(format!("expected {} split chunks, found {}", total, parts.len()).into());
+    }
+    for (i, (index, part_total, _)) in parts.iter().enumerate() {
+        if *index as usize != i || *part_total != total {
+            return Err("split chunk sequence is inconsistent".into());
+        }
+    }
+    Ok(parts
+        .iter()
+        .flat_map(|(_, _, data)| data.iter())
+        .copied()
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest =
+            "# file type length crc\n0001_tEXt.bin tEXt 24 0a1b2c3d\n\n0000_IHDR.bin IHDR\n";
+        let entries = parse_manifest(manifest).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "0001_tEXt.bin");
+        assert!(entries[0].1.matches("tEXt"));
+        assert!(entries[1].1.matches("IHDR"));
+
+        assert!(parse_manifest("lonely_field\n").is_err());
+        assert!(parse_manifest("0000_x.bin 1234\n").is_err());
+    }
+
+    #[test]
+    fn test_strip_cr() {
+        assert_eq!(strip_cr("line one\r\nline two\r\n"), "line one\nline two\n");
+        assert_eq!(strip_cr("unix\nonly"), "unix\nonly");
+        assert_eq!(strip_cr("lone\rcarriage return"), "lone\rcarriage return");
+    }
+
+    #[test]
+    fn test_is_yes() {
+        for answer in ["y\n", "Y\n", "yes\n", " YES \r\n"] {
+            assert!(is_yes(answer), "{:?}", answer);
+        }
+        for answer in ["\n", "n\n", "no\n", "yep\n", ""] {
+            assert!(!is_yes(answer), "{:?}", answer);
+        }
+    }
+
+    #[test]
+    fn test_holds_payload() {
+        let image = Png::from_chunks(vec![
+            chunk("IHDR", ""),
+            chunk("ruSt", "a"),
+            chunk("ruSt", "b"),
+        ]);
+        assert!(holds_payload(&image, &[chunk("ruSt", "b")]));
+        assert!(!holds_payload(&image, &[chunk("ruSt", "c")]));
+        assert!(!holds_payload(&image, &[chunk("prVt", "a")]));
+        assert!(holds_payload(
+            &image,
+            &[chunk("ruSt", "a"), chunk("ruSt", "b")]
+        ));
+        assert!(!holds_payload(
+            &image,
+            &[chunk("ruSt", "b"), chunk("ruSt", "a")]
+        ));
+        assert!(!holds_payload(&image, &[]));
+    }
+
+    #[test]
+    fn test_parse_crc() {
+        assert_eq!(parse_crc("0xAE426082"), Ok(0xAE42_6082));
+        assert_eq!(parse_crc("0Xae426082"), Ok(0xAE42_6082));
+        assert_eq!(parse_crc("2923585666"), Ok(0xAE42_6082));
+        assert!(parse_crc("AE426082").is_err());
+        assert!(parse_crc("0x1_0000_0000").is_err());
+        assert!(parse_crc("").is_err());
+    }
+
+    #[test]
+    fn test_parse_type_hex() {
+        assert!(parse_type_hex("72755374").unwrap().matches("ruSt"));
+        assert_eq!(
+            parse_type_hex("00ff10aB").unwrap().bytes(),
+            [0, 0xff, 0x10, 0xab]
+        );
+        assert!(parse_type_hex("7275537").is_err());
+        assert!(parse_type_hex("7275537g").is_err());
+        assert!(parse_type_hex("+1+2+3+4").is_err());
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("raw".parse(), Ok(OutputFormat::Raw));
+        assert_eq!("Pretty".parse(), Ok(OutputFormat::Pretty));
+        assert_eq!("hex".parse(), Ok(OutputFormat::Hex));
+        assert_eq!("AUTO".parse(), Ok(OutputFormat::Auto));
+        assert!("json".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_sorted_chunks() {
+        let chunks: Vec<Chunk> = [("IHDR", 13), ("tEXt", 40), ("IDAT", 40), ("IEND", 0)]
+            .iter()
+            .map(|(t, len)| Chunk::new(ChunkType::from_str(t).unwrap(), vec![0; *len]))
+            .collect();
+        let types = |sort, reverse| -> Vec<&str> {
+            sorted_chunks(&chunks, sort, reverse)
+                .iter()
+                .map(|c| c.chunk_type_str().unwrap())
+                .collect()
+        };
+        assert_eq!(
+            types(SortKey::Offset, false),
+            ["IHDR", "tEXt", "IDAT", "IEND"]
+        );
+        assert_eq!(
+            types(SortKey::Offset, true),
+            ["IEND", "IDAT", "tEXt", "IHDR"]
+        );
+        assert_eq!(
+            types(SortKey::Type, false),
+            ["IDAT", "IEND", "IHDR", "tEXt"]
+        );
+        // Equal lengths keep file order.
+        assert_eq!(
+            types(SortKey::Length, false),
+            ["IEND", "IHDR", "tEXt", "IDAT"]
+        );
+        assert_eq!("Length".parse(), Ok(SortKey::Length));
+        assert!("size".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn test_if_absent_from_str() {
+        assert_eq!("skip".parse(), Ok(IfAbsent::Skip));
+        assert_eq!("ERROR".parse(), Ok(IfAbsent::Error));
+        assert!("append".parse::<IfAbsent>().is_err());
+    }
+
+    #[test]
+    fn test_pad_payload() {
+        let padded = pad_payload(b"abc".to_vec(), 8).unwrap();
+        assert_eq!(padded, b"abc\x80\0\0\0\0");
+        assert_eq!(pad_payload(b"abcdefgh".to_vec(), 8).unwrap().len(), 16);
+        assert_eq!(pad_payload(Vec::new(), 4).unwrap(), b"\x80\0\0\0");
+        assert!(pad_payload(b"abc".to_vec(), 0).is_err());
+    }
+
+    #[test]
+    fn test_unpad_payload() {
+        for message in [&b""[..], b"abc", b"ends in zero\0", b"abcdefgh"] {
+            let padded = pad_payload(message.to_vec(), 8).unwrap();
+            assert_eq!(unpad_payload(&padded).unwrap(), message);
+        }
+        assert!(unpad_payload(b"no marker\0\0").is_err());
+        assert!(unpad_payload(b"").is_err());
+    }
+
+    fn split_chunks(data: &[u8], part_size: usize) -> Vec<Chunk> {
+        split_payload(data, part_size)
+            .unwrap()
+            .into_iter()
+            .map(|part| Chunk::new(ChunkType::from_str("ruSt").unwrap(), part))
+            .collect()
+    }
+
+    #[test]
+    fn test_sorted_by_count() {
+        let ty = |t: &str| ChunkType::from_str(t).unwrap();
+        let map = HashMap::from([
+            (ty("tEXt"), 3),
+            (ty("IDAT"), 42),
+            (ty("ruSt"), 1),
+            (ty("IEND"), 1),
+        ]);
+        let sorted: Vec<(String, usize)> = sorted_by_count(map)
+            .into_iter()
+            .map(|(t, n)| (t.to_string(), n))
+            .collect();
+        let expected = [("IDAT", 42), ("tEXt", 3), ("IEND", 1), ("ruSt", 1)];
+        assert_eq!(sorted, expected.map(|(t, n)| (t.to_string(), n)).to_vec());
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let chunks = vec![
+            chunk("IHDR", ""),
+            chunk("ruSt", "abcabc"),
+            chunk("tEXt", "aaa"),
+            chunk("ruSt", "xab"),
+        ];
+        let all = |_: &Chunk| true;
+        assert_eq!(
+            find_matches(&chunks, b"ab", all),
+            vec![(1, 0), (1, 3), (3, 1)]
+        );
+        assert_eq!(find_matches(&chunks, b"aa", all), vec![(2, 0), (2, 1)]);
+        assert!(find_matches(&chunks, b"zz", all).is_empty());
+
+        let text_only = |c: &Chunk| c.chunk_type().matches("tEXt");
+        assert_eq!(
+            find_matches(&chunks, b"a", text_only),
+            vec![(2, 0), (2, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_count_by_type() {
+        let chunks = vec![chunk("ruSt", "a"), chunk("tEXt", "a"), chunk("ruSt", "a")];
+        let matches = find_matches(&chunks, b"a", |_| true);
+        assert_eq!(
+            count_by_type(&chunks, &matches),
+            vec![(chunks[0].chunk_type(), 2), (chunks[1].chunk_type(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_keyword_rows() {
+        let chunks = vec![
+            chunk("IHDR", ""),
+            chunk("tEXt", "Title\0Dice"),
+            chunk("zTXt", "Comment\0\0xyz"),
+            chunk("iTXt", "Title\0\0\0\0\0Dice"),
+            chunk("tEXt", "no separator"),
+            chunk("ruSt", "Key\0not text"),
+        ];
+        let rows = keyword_rows(&chunks);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0], ("Title".to_string(), "Dice".to_string()));
+        assert_eq!(rows[1].0, "Comment");
+        assert!(rows[1].1.starts_with("<malformed zTXt"));
+        assert!(rows[2].1.contains("not decoded"));
+        assert_eq!(rows[3].0, "?");
+        assert!(rows[3].1.contains("malformed"));
+    }
+
+    #[test]
+    fn test_printable_only() {
+        assert_eq!(printable_only("\x1b[2Jtitle\x07"), ".[2Jtitle.");
+        assert_eq!(
+            printable_only("two\nlines\tand tabs"),
+            "two\nlines\tand tabs"
+        );
+        assert_eq!(printable_only("caf\u{e9} \u{9b}\x7f"), "caf\u{e9} ..");
+    }
+
+    #[test]
+    fn test_to_base64() {
+        // The RFC 4648 test vectors.
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(to_base64(input.as_bytes()), expected);
+        }
+        assert_eq!(to_base64(&[0xFF, 0xFE, 0x00]), "//4A");
+    }
+
+    #[test]
+    fn test_representation() {
+        assert_eq!("UTF-8".parse(), Ok(Representation::Utf8));
+        assert!("base32".parse::<Representation>().is_err());
+        assert_eq!(Representation::Utf8.render("h\u{e9}".as_bytes()), "h\u{e9}");
+        assert!(Representation::Utf8
+            .render(&[0xFF])
+            .starts_with("<not valid UTF-8"));
+        assert_eq!(Representation::Hex.render(b"hi"), "6869");
+        assert_eq!(Representation::Base64.render(b"hi"), "aGk=");
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(to_hex(&[]), "");
+        assert_eq!(to_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"Hello, pngme!\x00\x01\xffmore");
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "00000000  48 65 6c 6c 6f 2c 20 70 6e 67 6d 65 21 00 01 ff  |Hello, pngme!...|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  6d 6f 72 65                                      |more|"
+        );
+        assert_eq!(hexdump(&[]), "");
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let not_found: Box<dyn Error> = ChunkNotFound::new("ruSt").into();
+        assert_eq!(exit_code(not_found.as_ref()), EXIT_CHUNK_NOT_FOUND);
+
+        let parse: Box<dyn Error> = Png::try_from(&b"not a png"[..]).unwrap_err().into();
+        assert_eq!(exit_code(parse.as_ref()), EXIT_PARSE_ERROR);
+
+        let bad_type = ChunkType::from_str("ru5t").unwrap_err();
+        assert_eq!(exit_code(bad_type.as_ref()), EXIT_PARSE_ERROR);
+
+        let usage: Box<dyn Error> = UsageError::new("--keyword needs tEXt").into();
+        assert_eq!(exit_code(usage.as_ref()), EXIT_PARSE_ERROR);
+
+        let io: Box<dyn Error> = fs::read("/nonexistent/pngme.png").unwrap_err().into();
+        assert_eq!(exit_code(io.as_ref()), EXIT_IO_ERROR);
+    }
+
+    #[test]
+    fn test_error_context_keeps_exit_code() {
+        let result: Result<(), _> = Err(ChunkNotFound::new("ruSt").into());
+        let err = with_context(result, || "decode a.png".to_string()).unwrap_err();
+        assert_eq!(err.to_string(), "decode a.png: no ruSt chunk found");
+        assert_eq!(exit_code(err.as_ref()), EXIT_CHUNK_NOT_FOUND);
+        assert!(with_context(Ok(()), || unreachable!()).is_ok());
+    }
+
+    #[test]
+    fn test_charset_from_str() {
+        assert_eq!(Charset::from_str("utf8").unwrap(), Charset::Utf8);
+        assert_eq!(Charset::from_str("Latin1").unwrap(), Charset::Latin1);
+        assert_eq!(Charset::from_str("utf-16le").unwrap(), Charset::Utf16Le);
+        assert!(Charset::from_str("ebcdic").is_err());
+    }
+
+    #[test]
+    fn test_charset_decode() {
+        assert_eq!(Charset::Utf8.decode("héllo".as_bytes()).unwrap(), "héllo");
+        assert_eq!(Charset::Latin1.decode(&[104, 233, 108]).unwrap(), "hél");
+        assert_eq!(Charset::Utf16Le.decode(&[104, 0, 233, 0]).unwrap(), "hé");
+        assert_eq!(Charset::Utf16Be.decode(&[0, 104, 0, 233]).unwrap(), "hé");
+        // A surrogate pair: U+1F980
+        assert_eq!(
+            Charset::Utf16Be.decode(&[0xD8, 0x3E, 0xDD, 0x80]).unwrap(),
+            "\u{1F980}"
+        );
+    }
+
+    #[test]
+    fn test_charset_decode_errors() {
+        assert!(Charset::Utf8.decode(&[0xFF]).is_err());
+        assert!(Charset::Utf16Le.decode(&[104, 0, 105]).is_err());
+        assert!(Charset::Utf16Be.decode(&[0xD8, 0x3E]).is_err());
+    }
+
+    fn chunk(chunk_type: &str, data: &str) -> Chunk {
+        Chunk::new(
+            ChunkType::from_str(chunk_type).unwrap(),
+            data.as_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_diff_chunks() {
+        let a = vec![
+            chunk("IHDR", "h"),
+            chunk("tEXt", "a"),
+            chunk("IDAT", "x"),
+            chunk("IEND", ""),
+        ];
+        let b = vec![
+            chunk("IHDR", "h"),
+            chunk("IDAT", "y"),
+            chunk("ruSt", "secret"),
+            chunk("IEND", ""),
+        ];
+        let text = |s: &str| s.to_string();
+        assert_eq!(
+            diff_chunks(&a, &b),
+            vec![
+                ChunkDiff::Same {
+                    chunk_type: text("IHDR"),
+                    a: 0,
+                    b: 0
+                },
+                ChunkDiff::OnlyInA {
+                    chunk_type: text("tEXt"),
+                    a: 1
+                },
+                ChunkDiff::Differs {
+                    chunk_type: text("IDAT"),
+                    a: 2,
+                    b: 1
+                },
+                ChunkDiff::Same {
+                    chunk_type: text("IEND"),
+                    a: 3,
+                    b: 3
+                },
+                ChunkDiff::OnlyInB {
+                    chunk_type: text("ruSt"),
+                    b: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_chunks_repeated_types() {
+        let a = vec![chunk("IDAT", "1"), chunk("IDAT", "2")];
+        let b = vec![chunk("IDAT", "1"), chunk("IDAT", "2"), chunk("IDAT", "3")];
+        let diffs = diff_chunks(&a, &b);
+        assert_eq!(diffs.len(), 3);
+        assert!(matches!(diffs[1], ChunkDiff::Same { a: 1, b: 1, .. }));
+        assert!(matches!(diffs[2], ChunkDiff::OnlyInB { b: 2, .. }));
+    }
+
+    #[test]
+    fn test_numbered_path() {
+        assert_eq!(numbered_path("out.bin", 0), PathBuf::from("out.0.bin"));
+        assert_eq!(
+            numbered_path("dir/out.bin", 2),
+            PathBuf::from("dir/out.2.bin")
+        );
+        assert_eq!(numbered_path("out", 1), PathBuf::from("out.1"));
+    }
+
+    #[test]
+    fn test_split_payload_sizes() {
+        let parts = split_payload(b"abcdefghij", 4).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), SPLIT_HEADER_LEN + 4);
+        assert_eq!(parts[2].len(), SPLIT_HEADER_LEN + 2);
+    }
+
+    #[test]
+    fn test_split_payload_zero_size() {
+        assert!(split_payload(b"abc", 0).is_err());
+    }
+
+    #[test]
+    fn test_join_payload_out_of_order() {
+        let chunks = split_chunks(b"This is where your secret message will be!", 5);
+        let reversed: Vec<&Chunk> = chunks.iter().rev().collect();
+        let joined = join_payload(&reversed).unwrap();
+        assert_eq!(joined, b"This is where your secret message will be!");
+    }
+
+    #[test]
+    fn test_join_payload_empty_message() {
+        let chunks = split_chunks(b"", 5);
+        let refs: Vec<&Chunk> = chunks.iter().collect();
+        assert_eq!(join_payload(&refs).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_join_payload_missing_part() {
+        let chunks = split_chunks(b"abcdefghij", 4);
+        let refs: Vec<&Chunk> = vec![&chunks[0], &chunks[2]];
+        assert!(join_payload(&refs).is_err());
+    }
 }
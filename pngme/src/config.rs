@@ -0,0 +1,101 @@
+use crate::commands::UsageError;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Settings read from `$XDG_CONFIG_HOME/pngme/config.toml` (falling back to
+/// `~/.config/pngme/config.toml`). Only simple `key = "value"` lines are understood.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Chunk type used when a command is run without one.
+    pub default_chunk_type: Option<String>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("pngme").join("config.toml"))
+    }
+
+    /// Reads the config file. A missing file is not an error and yields the defaults.
+    pub fn load() -> Result<Config, Box<dyn Error>> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e).into())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::default();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", number + 1))?;
+            let value = value.trim().trim_matches('"').to_string();
+            // Unknown keys are ignored so newer config files keep working.
+            if key.trim() == "default_chunk_type" {
+                config.default_chunk_type = Some(value);
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Uses `chunk_type` when given, otherwise the configured `default_chunk_type`.
+pub fn resolve_chunk_type(chunk_type: Option<String>) -> Result<String, Box<dyn Error>> {
+    if let Some(chunk_type) = chunk_type {
+        return Ok(chunk_type);
+    }
+    Config::load()?.default_chunk_type.ok_or_else(|| {
+        UsageError::new("no chunk type given and no default_chunk_type configured").into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_default_chunk_type() {
+        let config = Config::parse("# pngme settings\ndefault_chunk_type = \"ruSt\"\n").unwrap();
+        assert_eq!(config.default_chunk_type, Some("ruSt".to_string()));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_parse_ignores_unknown_keys() {
+        let config = Config::parse("color = \"always\"").unwrap();
+        assert_eq!(config.default_chunk_type, None);
+    }
+
+    #[test]
+    fn test_parse_malformed_line() {
+        assert!(Config::parse("default_chunk_type").is_err());
+    }
+
+    #[test]
+    fn test_resolve_prefers_argument() {
+        let chunk_type = resolve_chunk_type(Some("TeSt".to_string())).unwrap();
+        assert_eq!(chunk_type, "TeSt");
+    }
+}
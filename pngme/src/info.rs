@@ -0,0 +1,421 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The decoded contents of an `IHDR` chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: u8,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+impl ImageHeader {
+    pub const LENGTH: usize = 13;
+
+    pub fn color_type_name(&self) -> &'static str {
+        match self.color_type {
+            0 => "Grayscale",
+            2 => "RGB",
+            3 => "Indexed",
+            4 => "Grayscale + Alpha",
+            6 => "RGBA",
+            _ => "Unknown",
+        }
+    }
+
+    pub fn interlace_name(&self) -> &'static str {
+        match self.interlace_method {
+            0 => "None",
+            1 => "Adam7",
+            _ => "Unknown",
+        }
+    }
+}
+
+impl TryFrom<&Chunk> for ImageHeader {
+    type Error = Box<dyn Error>;
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if !chunk.chunk_type().matches("IHDR") {
+            return Err(format!("expected IHDR chunk, found {}", chunk.chunk_type()).into());
+        }
+        let data = chunk.data();
+        if data.len() != Self::LENGTH {
+            return Err(format!(
+                "malformed IHDR: expected {} bytes, found {}",
+                Self::LENGTH,
+                data.len()
+            )
+            .into());
+        }
+        let header = ImageHeader {
+            width: u32::from_be_bytes(data[0..4].try_into()?),
+            height: u32::from_be_bytes(data[4..8].try_into()?),
+            bit_depth: data[8],
+            color_type: data[9],
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: data[12],
+        };
+
+        if header.width == 0 || header.height == 0 {
+            return Err("malformed IHDR: width and height must be non-zero".into());
+        }
+        let allowed_depths: &[u8] = match header.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            other => return Err(format!("malformed IHDR: unknown color type {}", other).into()),
+        };
+        if !allowed_depths.contains(&header.bit_depth) {
+            return Err(format!(
+                "malformed IHDR: bit depth {} not allowed for color type {}",
+                header.bit_depth, header.color_type
+            )
+            .into());
+        }
+        if header.interlace_method > 1 {
+            return Err(format!(
+                "malformed IHDR: unknown interlace method {}",
+                header.interlace_method
+            )
+            .into());
+        }
+        Ok(header)
+    }
+}
+
+/// The decoded contents of a `tIME` chunk: when the image was last modified, in UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModificationTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl ModificationTime {
+    pub const LENGTH: usize = 7;
+
+    /// The current time, as `encode --timestamp` records it.
+    pub fn now() -> Self {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self::from_unix_seconds(since_epoch.as_secs())
+    }
+
+    /// The UTC calendar time `secs` seconds after 1970-01-01 00:00:00, ignoring leap
+    /// seconds like Unix time does.
+    pub fn from_unix_seconds(secs: u64) -> Self {
+        let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+        // Days to a proleptic Gregorian date, counting eras of 400 years from 0000-03-01
+        // so leap days fall at the end of each year.
+        let days = days + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        ModificationTime {
+            year: year.min(u16::MAX as u64) as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        }
+    }
+
+    /// The 7-byte `tIME` layout: a big-endian year, then month, day, hour, minute and
+    /// second.
+    pub fn as_bytes(&self) -> [u8; Self::LENGTH] {
+        let [year_hi, year_lo] = self.year.to_be_bytes();
+        [
+            year_hi,
+            year_lo,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        ]
+    }
+
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk::new(
+            ChunkType::from_str("tIME").unwrap(),
+            self.as_bytes().to_vec(),
+        )
+    }
+}
+
+impl fmt::Display for ModificationTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for ModificationTime {
+    type Error = Box<dyn Error>;
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if !chunk.chunk_type().matches("tIME") {
+            return Err(format!("expected tIME chunk, found {}", chunk.chunk_type()).into());
+        }
+        let data = chunk.data();
+        if data.len() != Self::LENGTH {
+            return Err(format!(
+                "malformed tIME: expected {} bytes, found {}",
+                Self::LENGTH,
+                data.len()
+            )
+            .into());
+        }
+        let time = ModificationTime {
+            year: u16::from_be_bytes(data[0..2].try_into()?),
+            month: data[2],
+            day: data[3],
+            hour: data[4],
+            minute: data[5],
+            second: data[6],
+        };
+        // Second 60 is allowed for leap seconds.
+        if !(1..=12).contains(&time.month)
+            || !(1..=31).contains(&time.day)
+            || time.hour > 23
+            || time.minute > 59
+            || time.second > 60
+        {
+            return Err(format!("malformed tIME: {} is not a valid time", time).into());
+        }
+        Ok(time)
+    }
+}
+
+/// The decoded contents of an `sRGB` chunk: the image is in the sRGB color space and
+/// should be rendered with the given intent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Srgb {
+    pub rendering_intent: u8,
+}
+
+impl Srgb {
+    pub const LENGTH: usize = 1;
+
+    pub fn rendering_intent_name(&self) -> &'static str {
+        match self.rendering_intent {
+            0 => "Perceptual",
+            1 => "Relative colorimetric",
+            2 => "Saturation",
+            3 => "Absolute colorimetric",
+            _ => "Unknown",
+        }
+    }
+}
+
+impl TryFrom<&Chunk> for Srgb {
+    type Error = Box<dyn Error>;
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if !chunk.chunk_type().matches("sRGB") {
+            return Err(format!("expected sRGB chunk, found {}", chunk.chunk_type()).into());
+        }
+        match chunk.data() {
+            &[rendering_intent] => Ok(Srgb { rendering_intent }),
+            data => Err(format!(
+                "malformed sRGB: expected {} byte, found {}",
+                Self::LENGTH,
+                data.len()
+            )
+            .into()),
+        }
+    }
+}
+
+/// The decoded contents of a `gAMA` chunk. The file stores the gamma times 100000,
+/// so 45455 means 0.45455 (the usual 1/2.2).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gamma {
+    pub scaled: u32,
+}
+
+impl Gamma {
+    pub const LENGTH: usize = 4;
+    pub const SCALE: u32 = 100_000;
+}
+
+impl fmt::Display for Gamma {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:05}",
+            self.scaled / Self::SCALE,
+            self.scaled % Self::SCALE
+        )
+    }
+}
+
+impl TryFrom<&Chunk> for Gamma {
+    type Error = Box<dyn Error>;
+    fn try_from(chunk: &Chunk) -> Result<Self, Self::Error> {
+        if !chunk.chunk_type().matches("gAMA") {
+            return Err(format!("expected gAMA chunk, found {}", chunk.chunk_type()).into());
+        }
+        let data = chunk.data();
+        if data.len() != Self::LENGTH {
+            return Err(format!(
+                "malformed gAMA: expected {} bytes, found {}",
+                Self::LENGTH,
+                data.len()
+            )
+            .into());
+        }
+        Ok(Gamma {
+            scaled: u32::from_be_bytes(data.try_into()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ihdr_chunk(data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_image_header_from_chunk() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50, 0, 0, 0, 40, 8, 6, 0, 0, 1]);
+        let header = ImageHeader::try_from(&chunk).unwrap();
+        assert_eq!(header.width, 50);
+        assert_eq!(header.height, 40);
+        assert_eq!(header.bit_depth, 8);
+        assert_eq!(header.color_type_name(), "RGBA");
+        assert_eq!(header.interlace_name(), "Adam7");
+    }
+
+    #[test]
+    fn test_image_header_wrong_length() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50, 0, 0, 0, 40, 8, 6]);
+        assert!(ImageHeader::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_image_header_bad_color_type() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50, 0, 0, 0, 40, 8, 5, 0, 0, 0]);
+        assert!(ImageHeader::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_image_header_bad_bit_depth() {
+        let chunk = ihdr_chunk(&[0, 0, 0, 50, 0, 0, 0, 40, 4, 2, 0, 0, 0]);
+        assert!(ImageHeader::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_image_header_wrong_type() {
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 13]);
+        assert!(ImageHeader::try_from(&chunk).is_err());
+    }
+
+    fn time_chunk(data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str("tIME").unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_modification_time_from_chunk() {
+        let chunk = time_chunk(&[0x07, 0xea, 10, 14, 9, 5, 30]);
+        let time = ModificationTime::try_from(&chunk).unwrap();
+        assert_eq!(time.year, 2026);
+        assert_eq!(time.to_string(), "2026-10-14 09:05:30 UTC");
+    }
+
+    #[test]
+    fn test_modification_time_wrong_length() {
+        let chunk = time_chunk(&[0x07, 0xea, 10, 14, 9, 5]);
+        assert!(ModificationTime::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_modification_time_out_of_range() {
+        assert!(ModificationTime::try_from(&time_chunk(&[0x07, 0xea, 13, 1, 0, 0, 0])).is_err());
+        assert!(ModificationTime::try_from(&time_chunk(&[0x07, 0xea, 1, 0, 0, 0, 0])).is_err());
+        assert!(ModificationTime::try_from(&time_chunk(&[0x07, 0xea, 1, 1, 24, 0, 0])).is_err());
+        assert!(ModificationTime::try_from(&time_chunk(&[0x07, 0xea, 6, 30, 23, 59, 60])).is_ok());
+    }
+
+    #[test]
+    fn test_modification_time_from_unix_seconds() {
+        let epoch = ModificationTime::from_unix_seconds(0);
+        assert_eq!(epoch.to_string(), "1970-01-01 00:00:00 UTC");
+        let time = ModificationTime::from_unix_seconds(1_700_000_000);
+        assert_eq!(time.to_string(), "2023-11-14 22:13:20 UTC");
+        // The leap day and the day after it.
+        let leap_day = ModificationTime::from_unix_seconds(951_782_400);
+        assert_eq!(leap_day.to_string(), "2000-02-29 00:00:00 UTC");
+        let next = ModificationTime::from_unix_seconds(951_868_799);
+        assert_eq!(next.to_string(), "2000-02-29 23:59:59 UTC");
+        let march = ModificationTime::from_unix_seconds(951_868_800);
+        assert_eq!(march.to_string(), "2000-03-01 00:00:00 UTC");
+    }
+
+    #[test]
+    fn test_modification_time_chunk_round_trip() {
+        let time = ModificationTime::from_unix_seconds(1_792_000_000);
+        let chunk = time.to_chunk();
+        assert_eq!(chunk.length() as usize, ModificationTime::LENGTH);
+        assert_eq!(ModificationTime::try_from(&chunk).unwrap(), time);
+        assert_eq!(
+            ModificationTime::now().as_bytes().len(),
+            ModificationTime::LENGTH
+        );
+    }
+
+    #[test]
+    fn test_srgb_from_chunk() {
+        let chunk = Chunk::new(ChunkType::from_str("sRGB").unwrap(), vec![1]);
+        let srgb = Srgb::try_from(&chunk).unwrap();
+        assert_eq!(srgb.rendering_intent, 1);
+        assert_eq!(srgb.rendering_intent_name(), "Relative colorimetric");
+
+        let chunk = Chunk::new(ChunkType::from_str("sRGB").unwrap(), vec![0, 0]);
+        assert!(Srgb::try_from(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_gamma_from_chunk() {
+        let chunk = Chunk::new(
+            ChunkType::from_str("gAMA").unwrap(),
+            45455u32.to_be_bytes().to_vec(),
+        );
+        let gamma = Gamma::try_from(&chunk).unwrap();
+        assert_eq!(gamma.scaled, 45455);
+        assert_eq!(gamma.to_string(), "0.45455");
+        assert_eq!(Gamma { scaled: 100_000 }.to_string(), "1.00000");
+
+        let chunk = Chunk::new(ChunkType::from_str("gAMA").unwrap(), vec![0; 3]);
+        assert!(Gamma::try_from(&chunk).is_err());
+    }
+}
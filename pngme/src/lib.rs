@@ -0,0 +1,9 @@
+pub mod chunk;
+pub mod chunk_type;
+pub mod color;
+pub mod commands;
+pub mod config;
+pub mod info;
+pub mod png;
+pub mod progress;
+pub mod text;
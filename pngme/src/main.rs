@@ -1,68 +1,704 @@
-mod chunk;
-mod chunk_type;
-mod commands;
-mod png;
+use pngme::color::ColorChoice;
+use pngme::commands::{
+    self as other_commands, BatchOptions, Charset, DecodeOptions, EncodeOptions, FindOptions,
+    IfAbsent, IoOptions, OutputFormat, PrintOptions, RemoveFilter, Representation, SortKey,
+};
+use pngme::config;
 
-use crate::chunk::Chunk;
-use crate::chunk::ChunkError;
-use crate::chunk_type::ChunkType;
-use crate::commands as other_commands;
-use crate::png::Png;
-
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use log::{error, Level};
+use std::error::Error;
+use std::io::{self, IsTerminal, Write};
 
 #[derive(Parser)]
-#[command(name = "pngme", version, about = "A PNG steganography CLI")]
+#[command(
+    name = "pngme",
+    version,
+    about = "A PNG steganography CLI",
+    after_help = "Exit codes:
+  0  success
+  1  I/O or other error
+  2  input is not a valid PNG, or a chunk type is malformed
+  3  requested chunk type not found"
+)]
 struct Cli {
+    /// Report read/write progress of large files on stderr
+    #[arg(long, global = true)]
+    progress: bool,
     #[command(subcommand)]
     command: ParsedCommands,
 }
 
+/// Options shared by the commands that can change several files at once.
+#[derive(Args)]
+struct BatchArgs {
+    /// Further files to process the same way as FILE_PATH
+    #[arg(long = "files", value_name = "PATH", num_args = 1..)]
+    more_files: Vec<String>,
+    /// Write the results into this directory, keeping the original file names
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<String>,
+    /// Continue with the remaining files when one fails
+    #[arg(long)]
+    keep_going: bool,
+}
+
+impl BatchArgs {
+    fn is_batch(&self) -> bool {
+        !self.more_files.is_empty() || self.output_dir.is_some()
+    }
+
+    /// Runs `op` on FILE_PATH alone, or on every file with a summary in batch mode.
+    fn run(
+        self,
+        file_path: String,
+        op: impl Fn(&str) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.is_batch() {
+            return op(&file_path);
+        }
+        let mut files = vec![file_path];
+        files.extend(self.more_files);
+        other_commands::for_each_file(
+            &files,
+            &BatchOptions {
+                output_dir: self.output_dir,
+                keep_going: self.keep_going,
+            },
+            op,
+        )
+    }
+}
+
 #[derive(Subcommand)]
 enum ParsedCommands {
+    #[command(allow_missing_positional = true)]
     Encode {
         file_path: String,
-        chunk_type: String,
-        message: String,
+        /// Defaults to `default_chunk_type` from the config file
+        chunk_type: Option<String>,
+        message: Option<String>,
+        /// Read the message from a text file instead (`-` for stdin); CRLF line endings
+        /// are converted to LF
+        #[arg(long, value_name = "PATH")]
+        message_file: Option<String>,
+        /// Split the message into chunks carrying at most this many message bytes each,
+        /// plus an 8-byte sequence header
+        #[arg(long)]
+        split: Option<usize>,
+        /// Store this CRC instead of the computed one (produces an invalid PNG, for testing)
+        #[arg(long, hide = true)]
+        raw_crc: Option<u32>,
+        /// Store the message as a tEXt entry under this keyword (1-79 Latin-1 characters)
+        #[arg(long, conflicts_with = "split")]
+        keyword: Option<String>,
+        /// Write the modified PNG to stdout instead of back to FILE_PATH
+        #[arg(long)]
+        stdout: bool,
+        /// Pad the data to a multiple of N bytes: a 0x80 marker, then zeros
+        #[arg(long, value_name = "N", conflicts_with_all = ["split", "keyword"])]
+        pad_to: Option<usize>,
+        /// Only encode if no chunk of this type exists; otherwise skip (default) or error
+        #[arg(
+            long,
+            value_name = "skip|error",
+            num_args = 0..=1,
+            default_missing_value = "skip"
+        )]
+        if_absent: Option<IfAbsent>,
+        /// Don't warn about encoding into a critical or standard chunk type
+        #[arg(long)]
+        force: bool,
+        /// Give the chunk type as 8 hex digits instead, skipping validation (for testing).
+        /// pngme itself can't read a file back if the bytes aren't a valid type
+        #[arg(long, value_name = "HEX")]
+        type_hex: Option<String>,
+        /// Overwrite FILE_PATH without asking first (only asked on a terminal)
+        #[arg(long)]
+        force_overwrite: bool,
+        /// Also record the current time in the tIME chunk, replacing any existing one
+        #[arg(long)]
+        timestamp: bool,
+        /// Keep FILE_PATH's modification time after rewriting it
+        #[arg(long)]
+        preserve_mtime: bool,
+        #[command(flatten)]
+        batch: BatchArgs,
+        /// Do nothing if a chunk of this type already holds the same payload
+        #[arg(long)]
+        skip_duplicate: bool,
+        /// Insert after the first chunk of this type (e.g. IHDR) instead of before IEND
+        #[arg(long, value_name = "TYPE", conflicts_with = "before")]
+        after: Option<String>,
+        /// Insert before the first chunk of this type (e.g. IDAT) instead of before IEND
+        #[arg(long, value_name = "TYPE")]
+        before: Option<String>,
     },
     Decode {
         file_path: String,
-        chunk_type: String,
+        /// Defaults to `default_chunk_type` from the config file
+        chunk_type: Option<String>,
+        /// Reassemble a message that was encoded with --split
+        #[arg(long)]
+        split: bool,
+        /// Text encoding of the data: utf8, latin1, utf16le or utf16be [default: latin1 for
+        /// tEXt and zTXt, utf8 otherwise]
+        #[arg(long)]
+        charset: Option<Charset>,
+        /// Read chunks with a bad CRC instead of failing, warning about each
+        #[arg(long)]
+        no_crc_check: bool,
+        /// Strip padding written by encode --pad-to
+        #[arg(long)]
+        unpad: bool,
+        /// pretty decodes the text and adds a newline; raw writes the exact bytes; hex
+        /// prints them as hex digits; auto is pretty, or hex when the text won't decode
+        #[arg(long, value_name = "raw|hex|pretty|auto", default_value = "pretty")]
+        output_format: OutputFormat,
+        /// End the output with a NUL byte instead of a newline (not with raw output)
+        #[arg(long)]
+        null_terminated: bool,
+        /// Read a PNG embedded this many bytes into the file
+        #[arg(long, value_name = "BYTES", default_value_t = 0)]
+        offset: usize,
+        /// Show control characters as '.' (always on when stdout is a terminal; raw
+        /// output is never changed)
+        #[arg(long)]
+        only_printable: bool,
+        /// Fail unless the chunk's stored CRC is this value (decimal or 0x-prefixed hex)
+        #[arg(
+            long,
+            value_name = "CRC",
+            value_parser = other_commands::parse_crc,
+            conflicts_with = "split"
+        )]
+        expect_crc: Option<u32>,
+        /// Print the payload in each of these forms on its own labeled line, e.g.
+        /// --show utf8,hex,base64
+        #[arg(
+            long,
+            value_name = "utf8|hex|base64",
+            value_delimiter = ',',
+            conflicts_with_all = ["output_format", "null_terminated"]
+        )]
+        show: Vec<Representation>,
     },
     Remove {
         file_path: String,
+        /// Defaults to `default_chunk_type` from the config file
+        chunk_type: Option<String>,
+        /// Remove the chunk at this position instead of the first one of a type
+        #[arg(long, conflicts_with = "chunk_type")]
+        index: Option<usize>,
+        /// Remove every ancillary chunk instead of one chunk
+        #[arg(long, conflicts_with_all = ["chunk_type", "index", "unsafe_to_copy"])]
+        ancillary: bool,
+        /// Remove every chunk that isn't safe to copy (critical ones only with --force)
+        #[arg(long, conflicts_with_all = ["chunk_type", "index"])]
+        unsafe_to_copy: bool,
+        /// Keep FILE_PATH's modification time after rewriting it
+        #[arg(long)]
+        preserve_mtime: bool,
+        #[command(flatten)]
+        batch: BatchArgs,
+        /// Allow removing critical chunks such as IHDR, PLTE, IDAT or IEND
+        #[arg(long)]
+        force: bool,
+    },
+    #[command(allow_missing_positional = true)]
+    Extract {
+        file_path: String,
+        /// Defaults to `default_chunk_type` from the config file
+        chunk_type: Option<String>,
+        output: String,
+        /// Write every matching chunk to numbered files (out.0.bin, out.1.bin, ...)
+        #[arg(long)]
+        all: bool,
+        /// Reassemble a message that was encoded with --split into one file
+        #[arg(long, conflicts_with = "all")]
+        split: bool,
+    },
+    /// Write each chunk's data to its own file in OUT_DIR, with a manifest
+    Dump {
+        file_path: String,
+        out_dir: String,
+    },
+    /// Build a PNG from a directory written by dump, recomputing CRCs
+    Assemble {
+        in_dir: String,
+        out_file: String,
+    },
+    /// Write a small solid grayscale PNG to experiment on
+    New {
+        out_file: String,
+        width: u32,
+        height: u32,
+        /// Gray level of every pixel, from 0 (black) to 255 (white)
+        #[arg(long, default_value_t = 255)]
+        gray: u8,
+    },
+    /// Change the type of the first chunk of type FROM to TO
+    RenameType {
+        file_path: String,
+        from: String,
+        to: String,
+    },
+    /// Append the first chunk of CHUNK_TYPE in SRC to DST, unchanged
+    CopyChunk {
+        src: String,
+        dst: String,
         chunk_type: String,
     },
+    /// Compare the chunk structure of two PNGs
+    Diff {
+        file_a: String,
+        file_b: String,
+    },
+    #[command(visible_alias = "list")]
     Print {
         file_path: String,
+        /// Show a hex dump of each chunk's data (first 256 bytes)
+        #[arg(long)]
+        hexdump: bool,
+        /// With --hexdump, dump all of the data
+        #[arg(long, requires = "hexdump")]
+        full: bool,
+        /// Color chunks by kind: auto (only on a terminal, honoring NO_COLOR), always or never
+        #[arg(
+            long,
+            value_name = "WHEN",
+            default_value = "auto",
+            num_args = 0..=1,
+            default_missing_value = "always"
+        )]
+        color: ColorChoice,
+        /// Read chunks with a bad CRC instead of failing, warning about each
+        #[arg(long)]
+        no_crc_check: bool,
+        /// Print only the first N chunks (0 for all)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        limit: usize,
+        /// Show CRCs as decimal numbers instead of hex
+        #[arg(long)]
+        crc_dec: bool,
+        /// Say what each chunk type is for (e.g. IHDR: image header)
+        #[arg(long)]
+        describe: bool,
+        /// Order chunks by file offset, chunk type or data length
+        #[arg(long, value_name = "offset|type|length", default_value = "offset")]
+        sort: SortKey,
+        /// Reverse the order, e.g. --sort length --reverse lists the largest chunk first
+        #[arg(long)]
+        reverse: bool,
+        /// Read a PNG embedded this many bytes into the file
+        #[arg(long, value_name = "BYTES", default_value_t = 0)]
+        offset: usize,
+    },
+    /// Print the chunk list and print it again whenever the file changes
+    Watch {
+        file_path: String,
+    },
+    /// List the keywords and values of tEXt, zTXt and iTXt chunks
+    ListKeywords {
+        file_path: String,
+    },
+    /// Search chunk data for a string, listing where it occurs
+    Find {
+        file_path: String,
+        needle: String,
+        /// Only print the number of matches per chunk type
+        #[arg(long)]
+        count: bool,
+        /// Only search chunks of this type
+        #[arg(long = "type", value_name = "TYPE")]
+        chunk_type: Option<String>,
+        /// Match --type regardless of case (rust finds ruSt). Case is meaningful in chunk
+        /// types: it marks a chunk as critical, private, or safe to copy
+        #[arg(long, requires = "chunk_type")]
+        ignore_type_case: bool,
+    },
+    /// Check chunk ordering and the lengths of critical chunks
+    Verify {
+        file_path: String,
+        /// Color the result: green when the file is OK, red for each problem
+        #[arg(
+            long,
+            value_name = "WHEN",
+            default_value = "auto",
+            num_args = 0..=1,
+            default_missing_value = "always"
+        )]
+        color: ColorChoice,
+    },
+    /// Decode the image fully to check that real decoders can still render it
+    #[cfg(feature = "image-validate")]
+    Validate {
+        file_path: String,
+    },
+    /// Print the CRC-32 of the whole file, to check that a round trip changed nothing
+    Checksum {
+        file_path: String,
+    },
+    /// Find PNG signatures anywhere in a file and check whether each one parses
+    Scan {
+        file_path: String,
     },
+    /// Count the chunks and data bytes of each chunk type
+    Stats {
+        file_path: String,
+    },
+    /// Print a hash of the chunk types and lengths, ignoring chunk data
+    Fingerprint {
+        file_path: String,
+    },
+    Capacity {
+        file_path: String,
+    },
+    Info {
+        file_path: String,
+    },
+}
+
+impl ParsedCommands {
+    /// What the command is doing and to which file, used to prefix its error. Batch
+    /// runs name each file in their summary instead.
+    fn context(&self) -> Option<String> {
+        let context = match self {
+            ParsedCommands::Encode {
+                file_path, batch, ..
+            } if !batch.is_batch() => format!("encode into {}", file_path),
+            ParsedCommands::Remove {
+                file_path, batch, ..
+            } if !batch.is_batch() => format!("remove from {}", file_path),
+            ParsedCommands::Encode { .. } | ParsedCommands::Remove { .. } => return None,
+            ParsedCommands::Decode { file_path, .. } => format!("decode {}", file_path),
+            ParsedCommands::Extract { file_path, .. } => format!("extract from {}", file_path),
+            ParsedCommands::Dump { file_path, .. } => format!("dump {}", file_path),
+            ParsedCommands::Assemble {
+                in_dir, out_file, ..
+            } => format!("assemble {} into {}", in_dir, out_file),
+            ParsedCommands::New { out_file, .. } => format!("create {}", out_file),
+            ParsedCommands::RenameType { file_path, .. } => {
+                format!("rename-type in {}", file_path)
+            }
+            ParsedCommands::CopyChunk { src, dst, .. } => {
+                format!("copy-chunk from {} to {}", src, dst)
+            }
+            ParsedCommands::Diff { file_a, file_b } => format!("diff {} {}", file_a, file_b),
+            ParsedCommands::Print { file_path, .. } => format!("list {}", file_path),
+            ParsedCommands::Watch { file_path } => format!("watch {}", file_path),
+            ParsedCommands::ListKeywords { file_path } => format!("list-keywords {}", file_path),
+            ParsedCommands::Find { file_path, .. } => format!("find in {}", file_path),
+            ParsedCommands::Verify { file_path, .. } => format!("verify {}", file_path),
+            #[cfg(feature = "image-validate")]
+            ParsedCommands::Validate { file_path } => format!("validate {}", file_path),
+            ParsedCommands::Checksum { file_path } => format!("checksum {}", file_path),
+            ParsedCommands::Scan { file_path } => format!("scan {}", file_path),
+            ParsedCommands::Stats { file_path } => format!("stats {}", file_path),
+            ParsedCommands::Fingerprint { file_path } => format!("fingerprint {}", file_path),
+            ParsedCommands::Capacity { file_path } => format!("capacity {}", file_path),
+            ParsedCommands::Info { file_path } => format!("info {}", file_path),
+        };
+        Some(context)
+    }
+}
+
+/// Diagnostics go to stderr through `log`; `RUST_LOG` adjusts the level (default info).
+/// User-facing output such as decoded messages stays on stdout.
+fn init_logging() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .format(|buf, record| match record.level() {
+            Level::Error => writeln!(buf, "Error: {}", record.args()),
+            Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+            Level::Info => writeln!(buf, "{}", record.args()),
+            level => writeln!(buf, "[{}] {}", level, record.args()),
+        })
+        .init();
+}
+
+/// Works out encode's chunk type and message. Both positionals are optional, so a lone
+/// positional after FILE_PATH is the message, or the chunk type when --message-file
+/// supplies the message.
+fn message_or_file(
+    chunk_type: Option<String>,
+    message: Option<String>,
+    message_file: Option<String>,
+    io_options: IoOptions,
+) -> Result<(Option<String>, String), Box<dyn Error>> {
+    match (chunk_type, message, message_file) {
+        (Some(_), Some(_), Some(_)) => {
+            Err("give either MESSAGE or --message-file, not both".into())
+        }
+        (chunk_type, Some(message), None) => Ok((chunk_type, message)),
+        (None, Some(chunk_type), Some(path)) => Ok((
+            Some(chunk_type),
+            other_commands::read_message_file(&path, io_options)?,
+        )),
+        (chunk_type, None, Some(path)) => Ok((
+            chunk_type,
+            other_commands::read_message_file(&path, io_options)?,
+        )),
+        (Some(message), None, None) => Ok((None, message)),
+        (None, None, None) => Err("a MESSAGE or --message-file is required".into()),
+    }
 }
 
 fn main() {
+    init_logging();
     let cli = Cli::parse();
+    let io_options = IoOptions {
+        progress: cli.progress,
+        ..IoOptions::default()
+    };
 
-    match cli.command {
+    let context = cli.command.context();
+    let result = run(cli.command, io_options);
+    let result = match context {
+        Some(context) => other_commands::with_context(result, || context),
+        None => result,
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(other_commands::exit_code(e.as_ref()));
+    }
+}
+
+/// Runs one subcommand. Every failure is returned, so `main` reports it and picks the
+/// exit status in one place.
+fn run(command: ParsedCommands, io_options: IoOptions) -> Result<(), Box<dyn Error>> {
+    match command {
         ParsedCommands::Encode {
             file_path,
             chunk_type,
             message,
+            message_file,
+            split,
+            raw_crc,
+            keyword,
+            stdout,
+            pad_to,
+            if_absent,
+            force,
+            type_hex,
+            skip_duplicate,
+            after,
+            before,
+            force_overwrite,
+            timestamp,
+            preserve_mtime,
+            batch,
         } => {
-            other_commands::encode(&file_path, &chunk_type, &message);
+            let io_options = IoOptions {
+                preserve_mtime,
+                ..io_options
+            };
+            message_or_file(chunk_type, message, message_file, io_options).and_then(
+                |(chunk_type, message)| {
+                    let (chunk_type, type_hex) = match (type_hex, chunk_type) {
+                        (Some(_), Some(chunk_type)) => {
+                            return Err(format!(
+                                "--type-hex conflicts with CHUNK_TYPE {}",
+                                chunk_type
+                            )
+                            .into())
+                        }
+                        (Some(hex), None) => (hex, true),
+                        (None, chunk_type) => (config::resolve_chunk_type(chunk_type)?, false),
+                    };
+                    if stdout && batch.is_batch() {
+                        return Err(
+                            "--stdout cannot be combined with --files or --output-dir".into()
+                        );
+                    }
+                    let options = EncodeOptions {
+                        split,
+                        raw_crc,
+                        keyword,
+                        stdout,
+                        pad_to,
+                        if_absent,
+                        force,
+                        type_hex,
+                        skip_duplicate,
+                        after,
+                        before,
+                        // Files in --output-dir are fresh copies, so there's nothing to lose.
+                        force_overwrite: force_overwrite || batch.output_dir.is_some(),
+                        timestamp,
+                        io: io_options,
+                    };
+                    batch.run(file_path, |path| {
+                        other_commands::encode(path, &chunk_type, &message, &options)
+                    })
+                },
+            )
         }
         ParsedCommands::Decode {
             file_path,
             chunk_type,
-        } => {
-            other_commands::decode(&file_path, &chunk_type);
-        }
+            split,
+            charset,
+            no_crc_check,
+            unpad,
+            output_format,
+            null_terminated,
+            offset,
+            only_printable,
+            expect_crc,
+            show,
+        } => other_commands::decode(
+            &file_path,
+            &config::resolve_chunk_type(chunk_type)?,
+            &DecodeOptions {
+                split,
+                charset,
+                no_crc_check,
+                unpad,
+                output_format,
+                null_terminated,
+                offset,
+                only_printable: only_printable || io::stdout().is_terminal(),
+                expect_crc,
+                show,
+                io: io_options,
+            },
+        ),
         ParsedCommands::Remove {
             file_path,
             chunk_type,
+            index,
+            ancillary,
+            unsafe_to_copy,
+            force,
+            preserve_mtime,
+            batch,
         } => {
-            other_commands::remove(&file_path, &chunk_type);
+            let io_options = IoOptions {
+                preserve_mtime,
+                ..io_options
+            };
+            let chunk_type = match (index, ancillary, unsafe_to_copy) {
+                (None, false, false) => config::resolve_chunk_type(chunk_type)?,
+                _ => String::new(),
+            };
+            batch.run(file_path, |path| match (index, ancillary, unsafe_to_copy) {
+                (Some(index), _, _) => other_commands::remove_at(path, index, force, io_options),
+                (None, true, _) => other_commands::remove_matching(
+                    path,
+                    RemoveFilter::Ancillary,
+                    force,
+                    io_options,
+                ),
+                (None, _, true) => other_commands::remove_matching(
+                    path,
+                    RemoveFilter::UnsafeToCopy,
+                    force,
+                    io_options,
+                ),
+                (None, false, false) => {
+                    other_commands::remove(path, &chunk_type, force, io_options)
+                }
+            })
+        }
+        ParsedCommands::Extract {
+            file_path,
+            chunk_type,
+            output,
+            all,
+            split,
+        } => other_commands::extract(
+            &file_path,
+            &config::resolve_chunk_type(chunk_type)?,
+            &output,
+            all,
+            split,
+            io_options,
+        ),
+        ParsedCommands::Dump { file_path, out_dir } => {
+            other_commands::dump(&file_path, &out_dir, io_options)
+        }
+        ParsedCommands::Assemble { in_dir, out_file } => {
+            other_commands::assemble(&in_dir, &out_file, io_options)
+        }
+        ParsedCommands::New {
+            out_file,
+            width,
+            height,
+            gray,
+        } => other_commands::new_image(&out_file, width, height, gray, io_options),
+        ParsedCommands::RenameType {
+            file_path,
+            from,
+            to,
+        } => other_commands::rename_type(&file_path, &from, &to, io_options),
+        ParsedCommands::CopyChunk {
+            src,
+            dst,
+            chunk_type,
+        } => other_commands::copy_chunk(&src, &dst, &chunk_type, io_options),
+        ParsedCommands::Diff { file_a, file_b } => {
+            other_commands::diff(&file_a, &file_b, io_options)
+        }
+        ParsedCommands::Print {
+            file_path,
+            hexdump,
+            full,
+            color,
+            no_crc_check,
+            limit,
+            crc_dec,
+            offset,
+            describe,
+            sort,
+            reverse,
+        } => other_commands::print_chunks(
+            &file_path,
+            &PrintOptions {
+                hexdump,
+                full,
+                color: color.enabled(),
+                no_crc_check,
+                limit,
+                crc_dec,
+                offset,
+                describe,
+                sort,
+                reverse,
+                io: io_options,
+            },
+        ),
+        ParsedCommands::Watch { file_path } => other_commands::watch(&file_path, io_options),
+        ParsedCommands::ListKeywords { file_path } => {
+            other_commands::list_keywords(&file_path, io_options)
+        }
+        ParsedCommands::Find {
+            file_path,
+            needle,
+            count,
+            chunk_type,
+            ignore_type_case,
+        } => other_commands::find(
+            &file_path,
+            &needle,
+            &FindOptions {
+                count,
+                chunk_type,
+                ignore_type_case,
+                io: io_options,
+            },
+        ),
+        ParsedCommands::Verify { file_path, color } => {
+            other_commands::verify(&file_path, color.enabled(), io_options)
         }
-        ParsedCommands::Print { file_path } => {
-            other_commands::print_chunks(&file_path);
+        #[cfg(feature = "image-validate")]
+        ParsedCommands::Validate { file_path } => other_commands::validate(&file_path, io_options),
+        ParsedCommands::Checksum { file_path } => other_commands::checksum(&file_path, io_options),
+        ParsedCommands::Scan { file_path } => other_commands::scan(&file_path, io_options),
+        ParsedCommands::Stats { file_path } => other_commands::stats(&file_path, io_options),
+        ParsedCommands::Fingerprint { file_path } => {
+            other_commands::fingerprint(&file_path, io_options)
         }
+        ParsedCommands::Capacity { file_path } => other_commands::capacity(&file_path),
+        ParsedCommands::Info { file_path } => other_commands::info(&file_path, io_options),
     }
 }
@@ -2,16 +2,105 @@
 #![allow(unused_variables)]
 #![warn(dead_code)]
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::fs;
-use std::io::{self, BufReader, Read};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::str::FromStr;
 
 use crate::chunk::Chunk;
 use crate::chunk::ChunkError;
 use crate::chunk_type::ChunkType;
+use crate::info::{ImageHeader, ModificationTime};
+
+/// Reported for input shorter than the 8-byte signature, including empty files.
+const TOO_SMALL: &str = "file is empty or too small to be a PNG";
+
+/// Leading bytes of files commonly passed by mistake, checked when the PNG signature
+/// doesn't match so the error can say what the input actually is.
+const OTHER_FORMATS: [(&[u8], &str); 5] = [
+    (
+        b"\xEF\xBB\xBF",
+        "UTF-8 text (it starts with a byte order mark)",
+    ),
+    (b"%PDF", "PDF"),
+    (b"GIF8", "GIF"),
+    (b"\xFF\xD8", "JPEG"),
+    (b"RIFF", "RIFF (WebP, WAV or AVI)"),
+];
+
+fn too_many_chunks(max_chunks: usize) -> PngError {
+    PngError::new(format!("file has more than {} chunks", max_chunks))
+}
+
+fn missing_anchor(anchor: &str) -> PngError {
+    PngError::new(format!("no {} chunk to insert next to", anchor))
+}
+
+/// The error for input whose first 8 bytes aren't the PNG signature.
+fn wrong_signature(header: &[u8]) -> String {
+    match OTHER_FORMATS
+        .iter()
+        .find(|(magic, _)| header.starts_with(magic))
+    {
+        Some((_, format)) => format!("this looks like a {} file, not a PNG", format),
+        None => "header not correct".to_string(),
+    }
+}
+
+/// Largest uncompressed image `Png::solid_gray` will build, counting the filter byte
+/// at the start of each row. Its IDAT is stored uncompressed, so this caps the file too.
+const MAX_SOLID_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest payload of one uncompressed deflate block.
+const MAX_STORED_BLOCK: usize = 65_535;
+
+/// Wraps `data` in a zlib stream of uncompressed ("stored") deflate blocks. Decoders
+/// accept it like any other zlib data, and it needs no compressor.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(2 + data.len() + blocks * 5 + 4);
+    // CMF/FLG: deflate with a 32K window, no preset dictionary, fastest level.
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut pieces = data.chunks(MAX_STORED_BLOCK).peekable();
+    if pieces.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(piece) = pieces.next() {
+        let is_final = pieces.peek().is_none();
+        let len = piece.len() as u16;
+        out.push(is_final as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(piece);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// The Adler-32 checksum that ends a zlib stream.
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65_521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+/// Where a chunk sits in a file and what it is, without its data. Returned by
+/// [`Png::scan_headers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHeader {
+    /// Position of the chunk's length field, counted from the start of the stream.
+    pub offset: u64,
+    /// Length of the chunk's data, not counting the length, type and CRC fields.
+    pub length: u32,
+    pub chunk_type: ChunkType,
+}
 
 #[derive(Debug)]
 pub struct Png {
@@ -29,6 +118,42 @@ impl Png {
         }
     }
 
+    /// Builds an 8-bit grayscale image of one solid `level` (0 is black, 255 white):
+    /// IHDR, a single uncompressed IDAT with unfiltered rows, and IEND. Meant for
+    /// getting a file to experiment on, not for real images.
+    pub fn solid_gray(width: u32, height: u32, level: u8) -> Result<Self, PngError> {
+        if width == 0 || height == 0 {
+            return Err(PngError::new(
+                "width and height must be non-zero".to_string(),
+            ));
+        }
+        let row_len = width as usize + 1;
+        if row_len.saturating_mul(height as usize) > MAX_SOLID_BYTES {
+            return Err(PngError::new(format!(
+                "{}x{} is too large (at most {} bytes of pixels)",
+                width, height, MAX_SOLID_BYTES
+            )));
+        }
+        let mut header = Vec::with_capacity(ImageHeader::LENGTH);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        // Bit depth 8, grayscale, deflate, adaptive filtering, no interlace.
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+        let mut row = vec![level; row_len];
+        // Filter type 0: the row's bytes are stored as they are.
+        row[0] = 0;
+        let pixels = row.repeat(height as usize);
+
+        let chunk =
+            |chunk_type: &str, data| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data);
+        Ok(Png::from_chunks(vec![
+            chunk("IHDR", header),
+            chunk("IDAT", zlib_stored(&pixels)),
+            chunk("IEND", Vec::new()),
+        ]))
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         let bytes = fs::read(path)?;
         Png::try_from(bytes.as_slice()).map_err(|e| {
@@ -39,19 +164,223 @@ impl Png {
         })
     }
 
+    /// Parses a PNG from a stream such as stdin, one chunk at a time. Reads loop until
+    /// each length, type, data and CRC field is complete, so a pipe handing over a few
+    /// bytes per `read` parses the same as a file.
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("PNG parse error: {}", message),
+            )
+        };
+
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid(TOO_SMALL),
+            _ => e,
+        })?;
+        if header != Png::STANDARD_HEADER {
+            return Err(invalid(&wrong_signature(&header)));
+        }
+
+        let mut chunks = Vec::new();
+        loop {
+            // Reading the length through `take` tells a clean end of stream (no bytes)
+            // apart from one cut off inside the length field.
+            let mut chunk_bytes = Vec::with_capacity(12);
+            reader.by_ref().take(4).read_to_end(&mut chunk_bytes)?;
+            match chunk_bytes.len() {
+                0 => break,
+                _ if chunks.len() == ParseOptions::DEFAULT_MAX_CHUNKS => {
+                    return Err(invalid(
+                        &too_many_chunks(ParseOptions::DEFAULT_MAX_CHUNKS).to_string(),
+                    ))
+                }
+                4 => {}
+                _ => return Err(invalid("chunk too small")),
+            }
+            let len = u32::from_be_bytes(<[u8; 4]>::try_from(chunk_bytes.as_slice()).unwrap());
+
+            // `take` rather than a buffer sized from `len`, so a corrupt length can't
+            // allocate gigabytes before the stream runs out.
+            let rest = len as u64 + 4 + 4;
+            if reader.by_ref().take(rest).read_to_end(&mut chunk_bytes)? as u64 != rest {
+                return Err(invalid("incomplete chunk"));
+            }
+            let chunk = Chunk::try_from(chunk_bytes.as_slice())
+                .map_err(|e| invalid(&format!("chunk error: {}", e)))?;
+            chunks.push(chunk);
+        }
+
+        Ok(Png::from_chunks(chunks))
+    }
+
+    /// Lists every chunk's offset, length and type, seeking past the data and CRC
+    /// instead of reading them. Much cheaper than a full parse on large files when
+    /// payloads aren't needed, but CRCs aren't checked. The PNG must start at the
+    /// reader's current position.
+    pub fn scan_headers<R: Read + Seek>(mut reader: R) -> io::Result<Vec<ChunkHeader>> {
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("PNG parse error: {}", message),
+            )
+        };
+
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid(TOO_SMALL),
+            _ => e,
+        })?;
+        if header != Png::STANDARD_HEADER {
+            return Err(invalid(&wrong_signature(&header)));
+        }
+
+        let mut headers = Vec::new();
+        let mut position = start + 8;
+        while position < end {
+            if headers.len() == ParseOptions::DEFAULT_MAX_CHUNKS {
+                return Err(invalid(
+                    &too_many_chunks(ParseOptions::DEFAULT_MAX_CHUNKS).to_string(),
+                ));
+            }
+            let mut fields = [0u8; 8];
+            reader
+                .read_exact(&mut fields)
+                .map_err(|_| invalid("chunk too small"))?;
+            let length = u32::from_be_bytes(fields[..4].try_into().unwrap());
+            let chunk_type = ChunkType::try_from(<[u8; 4]>::try_from(&fields[4..]).unwrap())
+                .map_err(|e| invalid(&format!("chunk error: {}", e)))?;
+            // Seeking past the end succeeds, so check against the length up front.
+            let next = position + 8 + length as u64 + 4;
+            if next > end {
+                return Err(invalid("incomplete chunk"));
+            }
+            headers.push(ChunkHeader {
+                offset: position - start,
+                length,
+                chunk_type,
+            });
+            reader.seek(SeekFrom::Start(next))?;
+            position = next;
+        }
+        Ok(headers)
+    }
+
     pub fn append_chunk(&mut self, chunk: Chunk) {
         self.chunks.push(chunk);
     }
 
+    /// Stores `message` in a new chunk of `chunk_type` just before `IEND`: what the
+    /// `encode` command does, without the file handling. [`Png::data_of_type`] reads it
+    /// back.
+    pub fn encode_message(
+        &mut self,
+        chunk_type: &str,
+        message: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let chunk = Chunk::try_new(ChunkType::from_str(chunk_type)?, message.to_vec())?;
+        self.append_chunk(chunk);
+        self.finalize()?;
+        Ok(())
+    }
+
     pub fn remove_first_chunk(&mut self, chunk_type: &str) -> Result<Chunk, PngError> {
-        let matched_chunk_type = ChunkType::from_str(chunk_type)
+        ChunkType::from_str(chunk_type)
             .map_err(|e| PngError::new(format!("Invalid chunk type: {}", e)))?;
-        for i in 0..self.chunks.len() {
-            if *self.chunks[i].chunk_type() == matched_chunk_type {
-                return Ok(self.chunks.remove(i));
-            }
+        match self.index_of_type(chunk_type) {
+            Some(index) => Ok(self.chunks.remove(index)),
+            None => Err(PngError::new("No matching chunk found".to_owned())),
+        }
+    }
+
+    /// Copies every ancillary chunk of `other`, in order, after this file's existing
+    /// chunks but ahead of its `IEND`. Critical chunks are skipped since duplicating
+    /// `IHDR`, `PLTE`, `IDAT` or `IEND` would corrupt the image.
+    pub fn merge_ancillary_from(&mut self, other: &Png) {
+        let insert_at = self.index_of_type("IEND").unwrap_or(self.chunks.len());
+        let ancillary = other
+            .chunks
+            .iter()
+            .filter(|c| !c.chunk_type().is_critical())
+            .cloned();
+        self.chunks.splice(insert_at..insert_at, ancillary);
+    }
+
+    /// Swaps in `chunk` at `index`, returning the chunk it replaced.
+    pub fn replace_chunk(&mut self, index: usize, chunk: Chunk) -> Result<Chunk, PngError> {
+        let err = self.index_out_of_range(index);
+        let slot = self.chunks.get_mut(index).ok_or(err)?;
+        Ok(std::mem::replace(slot, chunk))
+    }
+
+    /// Inserts `chunk` at `index`, shifting later chunks along. `index` may equal the
+    /// chunk count to append.
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> Result<(), PngError> {
+        if index > self.chunks.len() {
+            return Err(self.index_out_of_range(index));
+        }
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Inserts `chunk` just before the first chunk of type `anchor`, returning the index
+    /// it now has so further chunks can follow it. Fails if there is no such chunk.
+    pub fn insert_before_type(&mut self, anchor: &str, chunk: Chunk) -> Result<usize, PngError> {
+        let index = self
+            .index_of_type(anchor)
+            .ok_or_else(|| missing_anchor(anchor))?;
+        self.chunks.insert(index, chunk);
+        Ok(index)
+    }
+
+    /// Inserts `chunk` just after the first chunk of type `anchor`, returning the index
+    /// it now has. Fails if there is no such chunk. With several `IDAT` chunks, anchoring
+    /// on `IDAT` splits their run, which must be consecutive.
+    pub fn insert_after_type(&mut self, anchor: &str, chunk: Chunk) -> Result<usize, PngError> {
+        let index = self
+            .index_of_type(anchor)
+            .ok_or_else(|| missing_anchor(anchor))?
+            + 1;
+        self.chunks.insert(index, chunk);
+        Ok(index)
+    }
+
+    /// Removes the chunk at `index`, returning it. Useful when a file holds several
+    /// chunks of the same type and only a specific one should go.
+    pub fn remove_chunk_at(&mut self, index: usize) -> Result<Chunk, PngError> {
+        if index >= self.chunks.len() {
+            return Err(self.index_out_of_range(index));
         }
-        Err(PngError::new("No matching chunk found".to_owned()))
+        Ok(self.chunks.remove(index))
+    }
+
+    /// Removes every chunk `predicate` accepts, returning them in file order.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&Chunk) -> bool) -> Vec<Chunk> {
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| predicate(chunk));
+        self.chunks = kept;
+        removed
+    }
+
+    /// Keeps only the chunks `f` accepts, like [`Vec::retain`]. IHDR and IEND are
+    /// always kept so a loose predicate (e.g. a size threshold) can't leave the file
+    /// without its header or end marker; use [`Png::remove_where`] to drop those too.
+    pub fn retain_chunks<F: FnMut(&Chunk) -> bool>(&mut self, mut f: F) {
+        self.remove_where(|chunk| !(chunk.is_ihdr() || chunk.is_iend() || f(chunk)));
+    }
+
+    fn index_out_of_range(&self, index: usize) -> PngError {
+        PngError::new(format!(
+            "chunk index {} out of range (file has {} chunks)",
+            index,
+            self.chunks.len()
+        ))
     }
 
     pub fn header(&self) -> &[u8; 8] {
@@ -62,11 +391,232 @@ impl Png {
         &self.chunks
     }
 
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Mutable access to every chunk for in-place edits. `Chunk`'s setters keep each
+    /// chunk's length and CRC consistent with its data.
+    pub fn chunks_mut(&mut self) -> &mut [Chunk] {
+        &mut self.chunks
+    }
+
     pub fn chunk_by_type(&self, chunk_type: &str) -> Option<&Chunk> {
-        let chunk_type_from_str = ChunkType::from_str(chunk_type).ok()?;
         self.chunks
             .iter()
-            .find(|c| *c.chunk_type() == chunk_type_from_str)
+            .find(|c| c.chunk_type().matches(chunk_type))
+    }
+
+    pub fn chunk_by_type_mut(&mut self, chunk_type: &str) -> Option<&mut Chunk> {
+        self.chunks
+            .iter_mut()
+            .find(|c| c.chunk_type().matches(chunk_type))
+    }
+
+    /// Position of the first chunk of the given type.
+    pub fn index_of_type(&self, chunk_type: &str) -> Option<usize> {
+        self.chunks
+            .iter()
+            .position(|c| c.chunk_type().matches(chunk_type))
+    }
+
+    /// Every chunk of the given type, in file order.
+    pub fn chunks_by_type(&self, chunk_type: &str) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|c| c.chunk_type().matches(chunk_type))
+            .collect()
+    }
+
+    /// The data of every chunk of the given type joined in file order, for payloads
+    /// simply cut into consecutive chunks the way `IDAT` is. Empty if there are none.
+    /// Parts written by `encode --split` carry sequence headers and are reassembled by
+    /// `decode --split` instead.
+    pub fn data_of_type(&self, chunk_type: &str) -> Vec<u8> {
+        self.chunks_by_type(chunk_type)
+            .into_iter()
+            .flat_map(|c| c.data().iter().copied())
+            .collect()
+    }
+
+    /// Total data length of each chunk type, e.g. how many bytes are `IDAT` versus
+    /// hidden payload chunks.
+    pub fn data_bytes_by_type(&self) -> HashMap<ChunkType, usize> {
+        let mut totals = HashMap::new();
+        for chunk in self.chunks() {
+            *totals.entry(chunk.chunk_type().clone()).or_insert(0) += chunk.data().len();
+        }
+        totals
+    }
+
+    /// How many chunks there are of each type, e.g. 42 `IDAT`s and one `ruSt`.
+    pub fn chunk_counts_by_type(&self) -> HashMap<ChunkType, usize> {
+        let mut counts = HashMap::new();
+        for chunk in self.chunks() {
+            *counts.entry(chunk.chunk_type().clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// A CRC-32 of each chunk's type and length, in file order. It deliberately leaves
+    /// out chunk data and CRCs, so two files with the same chunk layout match even when
+    /// their contents differ, while an added, removed, reordered or resized chunk
+    /// changes it.
+    pub fn fingerprint(&self) -> u32 {
+        let layout: Vec<u8> = self
+            .chunks
+            .iter()
+            .flat_map(|c| {
+                c.chunk_type()
+                    .bytes()
+                    .into_iter()
+                    .chain(c.length().to_be_bytes())
+            })
+            .collect();
+        crc::crc32::checksum_ieee(&layout)
+    }
+
+    /// Decodes the `IHDR` chunk, erroring if it is missing or malformed.
+    pub fn image_header(&self) -> Result<ImageHeader, PngError> {
+        let ihdr = self
+            .chunk_by_type("IHDR")
+            .ok_or_else(|| PngError::new("IHDR chunk is missing".to_string()))?;
+        ImageHeader::try_from(ihdr).map_err(|e| PngError::new(e.to_string()))
+    }
+
+    /// Decodes the `tIME` chunk if there is one, erroring if it is malformed.
+    pub fn last_modified(&self) -> Result<Option<ModificationTime>, PngError> {
+        self.chunk_by_type("tIME")
+            .map(|chunk| {
+                ModificationTime::try_from(chunk).map_err(|e| PngError::new(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// An APNG announces itself with an `acTL` chunk before the first `IDAT`.
+    pub fn is_animated(&self) -> bool {
+        self.chunk_by_type("acTL").is_some()
+    }
+
+    /// Makes sure the chunk list ends with exactly one zero-length `IEND`, moving an
+    /// existing `IEND` to the end or appending a fresh one if there is none. Errors if
+    /// there is more than one `IEND` or it carries data.
+    ///
+    /// `as_bytes` serializes the chunks exactly as they are; normalization only happens
+    /// when this is called (`encode` does so before writing).
+    pub fn finalize(&mut self) -> Result<(), PngError> {
+        let iend_count = self.chunks_by_type("IEND").len();
+        if iend_count > 1 {
+            return Err(PngError::new(format!(
+                "expected one IEND chunk, found {}",
+                iend_count
+            )));
+        }
+        let iend = match self.index_of_type("IEND") {
+            Some(index) => self.chunks.remove(index),
+            None => Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new()),
+        };
+        if iend.length() != 0 {
+            return Err(PngError::new("IEND chunk must be empty".to_string()));
+        }
+        self.chunks.push(iend);
+        Ok(())
+    }
+
+    /// Reorders the chunks into the layout the spec recommends, without touching any
+    /// chunk's data: `IHDR`, then the chunks that came before the first `IDAT` (plus any
+    /// stray `PLTE`), the `IDAT`s as one block, everything else that followed the first
+    /// `IDAT`, and finally `IEND`. Chunks within each group keep their relative order, so
+    /// a file that already passes `validate_order` is left unchanged. A chunk wedged
+    /// between two `IDAT`s moves to just after the block; nothing ever moves from after
+    /// the image data to before it, since APNG frames and their controls depend on that.
+    pub fn canonicalize(&mut self) {
+        let first_idat = self.index_of_type("IDAT");
+        let mut keyed: Vec<(u8, Chunk)> = self
+            .chunks
+            .drain(..)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let group = match chunk.chunk_type_str() {
+                    Some("IHDR") => 0,
+                    Some("PLTE") => 1,
+                    Some("IDAT") => 2,
+                    Some("IEND") => 4,
+                    _ if first_idat.is_none_or(|first| index < first) => 1,
+                    _ => 3,
+                };
+                (group, chunk)
+            })
+            .collect();
+        // `sort_by_key` is stable, which is what keeps each group in file order.
+        keyed.sort_by_key(|(group, _)| *group);
+        self.chunks = keyed.into_iter().map(|(_, chunk)| chunk).collect();
+    }
+
+    /// Checks the chunk ordering rules from the PNG spec and reports every violation
+    /// rather than stopping at the first: `IHDR` first, `IEND` last, `PLTE` before the
+    /// first `IDAT`, and no other chunks between consecutive `IDAT`s.
+    pub fn validate_order(&self) -> Result<(), Vec<OrderError>> {
+        let mut errors = Vec::new();
+        if !self.chunks.first().is_some_and(|c| c.is_ihdr()) {
+            errors.push(OrderError::IhdrNotFirst);
+        }
+        if !self.chunks.last().is_some_and(|c| c.is_iend()) {
+            errors.push(OrderError::IendNotLast);
+        }
+
+        let idat: Vec<usize> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.chunk_type().matches("IDAT"))
+            .map(|(i, _)| i)
+            .collect();
+        if let (Some(&first), Some(&last)) = (idat.first(), idat.last()) {
+            for (index, chunk) in self.chunks.iter().enumerate() {
+                if chunk.chunk_type().matches("PLTE") && index > first {
+                    errors.push(OrderError::PlteAfterIdat { index });
+                }
+            }
+            if last - first + 1 != idat.len() {
+                errors.push(OrderError::IdatNotContiguous { first, last });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks the data length of the critical chunks whose size the spec fixes: `IHDR`
+    /// must be 13 bytes, `IEND` empty, and `PLTE` 1 to 256 three-byte entries. Reports
+    /// every violation.
+    pub fn validate_lengths(&self) -> Result<(), Vec<ChunkLengthError>> {
+        let mut errors = Vec::new();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let len = chunk.data().len();
+            let expected = match chunk.chunk_type_str() {
+                Some("IHDR") if len != ImageHeader::LENGTH => "13 bytes",
+                Some("IEND") if len != 0 => "0 bytes",
+                Some("PLTE") if len == 0 || len > 768 || len % 3 != 0 => {
+                    "a multiple of 3 from 3 to 768"
+                }
+                _ => continue,
+            };
+            errors.push(ChunkLengthError {
+                index,
+                chunk_type: chunk.chunk_type().clone(),
+                found: len,
+                expected,
+            });
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
@@ -98,23 +648,152 @@ impl fmt::Display for PngError {
 
 impl std::error::Error for PngError {}
 
+/// A violation of the chunk ordering rules, as reported by [`Png::validate_order`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum OrderError {
+    /// The first chunk is not `IHDR` (or there are no chunks).
+    IhdrNotFirst,
+    /// The last chunk is not `IEND` (or there are no chunks).
+    IendNotLast,
+    /// A `PLTE` chunk at `index` comes after the first `IDAT`.
+    PlteAfterIdat { index: usize },
+    /// Other chunks sit between the `IDAT` at `first` and the one at `last`.
+    IdatNotContiguous { first: usize, last: usize },
+}
+
+/// A critical chunk whose data length breaks the spec, as reported by
+/// [`Png::validate_lengths`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChunkLengthError {
+    pub index: usize,
+    pub chunk_type: ChunkType,
+    pub found: usize,
+    pub expected: &'static str,
+}
+
+impl fmt::Display for ChunkLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at index {} has {} bytes, expected {}",
+            self.chunk_type, self.index, self.found, self.expected
+        )
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IhdrNotFirst => write!(f, "IHDR is not the first chunk"),
+            Self::IendNotLast => write!(f, "IEND is not the last chunk"),
+            Self::PlteAfterIdat { index } => {
+                write!(f, "PLTE at index {} comes after the first IDAT", index)
+            }
+            Self::IdatNotContiguous { first, last } => write!(
+                f,
+                "IDAT chunks between index {} and {} are not contiguous",
+                first, last
+            ),
+        }
+    }
+}
+
+/// Settings for [`Png::try_from_with_options`]. The default is what `try_from` uses.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Reject chunks whose stored CRC doesn't match their type and data.
+    pub verify_crc: bool,
+    /// Fail once the file holds more than this many chunks. A crafted file made of
+    /// millions of empty chunks is only a few bytes each on disk but far more in memory,
+    /// so this bounds the work alongside the per-chunk length limit.
+    pub max_chunks: usize,
+    /// Skip this many leading bytes first, for a PNG embedded in another file. The
+    /// signature must start exactly there.
+    pub offset: usize,
+    /// Stop after the first `IEND` chunk and ignore whatever follows it, for a PNG
+    /// followed by other data.
+    pub stop_at_iend: bool,
+}
+
+impl ParseOptions {
+    pub const DEFAULT_MAX_CHUNKS: usize = 100_000;
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            verify_crc: true,
+            max_chunks: Self::DEFAULT_MAX_CHUNKS,
+            offset: 0,
+            stop_at_iend: false,
+        }
+    }
+}
+
 impl TryFrom<&[u8]> for Png {
     type Error = PngError;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Png::try_from_with_options(bytes, &ParseOptions::default())
+    }
+}
+
+impl Png {
+    /// Parses like `try_from` but accepts chunks whose CRC doesn't match, so data can
+    /// still be recovered from a damaged file. Structural errors are still rejected.
+    pub fn from_bytes_lenient(bytes: &[u8]) -> Result<Self, PngError> {
+        let options = ParseOptions {
+            verify_crc: false,
+            ..ParseOptions::default()
+        };
+        Png::try_from_with_options(bytes, &options)
+    }
+
+    /// Every offset in `bytes` where the 8-byte PNG signature begins, in order. Used to
+    /// find PNGs embedded in or concatenated with other data.
+    pub fn find_signatures(bytes: &[u8]) -> Vec<usize> {
+        bytes
+            .windows(Png::STANDARD_HEADER.len())
+            .enumerate()
+            .filter(|(_, window)| *window == Png::STANDARD_HEADER)
+            .map(|(offset, _)| offset)
+            .collect()
+    }
+
+    /// Parses a PNG that starts `offset` bytes into `bytes`, e.g. one carved out of a
+    /// larger container. Everything before the offset is ignored.
+    pub fn from_bytes_at_offset(bytes: &[u8], offset: usize) -> Result<Self, PngError> {
+        let options = ParseOptions {
+            offset,
+            ..ParseOptions::default()
+        };
+        Png::try_from_with_options(bytes, &options)
+    }
+
+    pub fn try_from_with_options(bytes: &[u8], options: &ParseOptions) -> Result<Self, PngError> {
+        let bytes = match options.offset {
+            0 => bytes,
+            offset => bytes
+                .get(offset..)
+                .filter(|rest| rest.starts_with(&Png::STANDARD_HEADER))
+                .ok_or_else(|| PngError::new(format!("no PNG signature at offset {}", offset)))?,
+        };
         if bytes.len() < 8 {
-            return Err(PngError::new("not enough bytes".to_string()));
+            return Err(PngError::new(TOO_SMALL.to_string()));
         }
 
         let (header, chunk_list) = bytes.split_at(8);
         if header != Png::STANDARD_HEADER {
-            return Err(PngError::new("header not correct".to_string()));
+            return Err(PngError::new(wrong_signature(header)));
         }
 
         let mut chunk_vec = Vec::new();
         let mut chunk_list = chunk_list;
 
         while !chunk_list.is_empty() {
+            if chunk_vec.len() == options.max_chunks {
+                return Err(too_many_chunks(options.max_chunks));
+            }
             if chunk_list.len() < 8 {
                 return Err(PngError::new("chunk too small".to_string()));
             }
@@ -123,17 +802,25 @@ impl TryFrom<&[u8]> for Png {
                 .map_err(|_| PngError::new("failed to read chunk length".to_string()))?;
             let len = u32::from_be_bytes(len_bytes) as usize;
 
-            let full_chunk_len = 4 + 4 + len + 4;
-            if chunk_list.len() < full_chunk_len {
-                return Err(PngError::new("incomplete chunk".to_string()));
-            }
+            let full_chunk_len = len
+                .checked_add(4 + 4 + 4)
+                .filter(|&full| full <= chunk_list.len())
+                .ok_or_else(|| PngError::new("incomplete chunk".to_string()))?;
 
             let (chunk_bytes, rest) = chunk_list.split_at(full_chunk_len);
-            let chunk = Chunk::try_from(chunk_bytes)
-                .map_err(|e| PngError::new(format!("chunk error: {}", e)))?;
+            let chunk = if options.verify_crc {
+                Chunk::try_from(chunk_bytes)
+            } else {
+                Chunk::from_bytes_lenient(chunk_bytes)
+            }
+            .map_err(|e| PngError::new(format!("chunk error: {}", e)))?;
 
+            let is_iend = chunk.is_iend();
             chunk_vec.push(chunk);
             chunk_list = rest;
+            if is_iend && options.stop_at_iend {
+                break;
+            }
         }
 
         Ok(Png {
@@ -173,7 +860,10 @@ mod tests {
         Png::from_chunks(chunks)
     }
 
-    fn chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk> {
+    fn chunk_from_strings(
+        chunk_type: &str,
+        data: &str,
+    ) -> Result<Chunk, Box<dyn std::error::Error>> {
         use std::str::FromStr;
 
         let chunk_type = ChunkType::from_str(chunk_type)?;
@@ -208,6 +898,163 @@ mod tests {
         assert!(png.is_ok());
     }
 
+    /// Hands out at most one byte per `read`, like a slow pipe.
+    struct OneByteReader<'a>(&'a [u8]);
+
+    impl Read for OneByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_from_reader_one_byte_at_a_time() {
+        let png = Png::from_reader(OneByteReader(&PNG_FILE)).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_from_reader_truncated() {
+        for end in [4, 10, 13, PNG_FILE.len() - 1] {
+            let result = Png::from_reader(OneByteReader(&PNG_FILE[..end]));
+            assert_eq!(
+                result.unwrap_err().kind(),
+                io::ErrorKind::InvalidData,
+                "cut at {}",
+                end
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_reader_huge_length() {
+        let mut bytes = Png::STANDARD_HEADER.to_vec();
+        bytes.extend(u32::MAX.to_be_bytes());
+        bytes.extend(b"ruSt");
+        assert!(Png::from_reader(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_other_format_detected() {
+        let cases: [(&[u8], &str); 3] = [
+            (b"\xEF\xBB\xBFhello, world", "UTF-8 text"),
+            (b"%PDF-1.7\n%more", "a PDF file"),
+            (b"\xFF\xD8\xFF\xE0\0\x10JFIF", "a JPEG file"),
+        ];
+        for (bytes, expected) in cases {
+            let err = Png::try_from(bytes).unwrap_err().to_string();
+            assert!(err.contains(expected), "{}", err);
+            assert!(err.ends_with("not a PNG"), "{}", err);
+        }
+        let err = Png::from_reader(&b"GIF89a\x01\0\x01\0"[..]).unwrap_err();
+        assert!(err.to_string().contains("a GIF file"), "{}", err);
+    }
+
+    #[test]
+    fn test_find_signatures() {
+        let mut bytes = b"junk".to_vec();
+        bytes.extend(PNG_FILE);
+        bytes.extend(b"more");
+        bytes.extend(Png::STANDARD_HEADER);
+        assert_eq!(
+            Png::find_signatures(&bytes),
+            vec![4, 4 + PNG_FILE.len() + 4]
+        );
+        assert!(Png::find_signatures(&Png::STANDARD_HEADER[..7]).is_empty());
+    }
+
+    #[test]
+    fn test_from_bytes_at_offset() {
+        let mut bytes = b"container header ".to_vec();
+        let offset = bytes.len();
+        bytes.extend(PNG_FILE);
+        let png = Png::from_bytes_at_offset(&bytes, offset).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+
+        for wrong in [offset - 1, offset + 1, bytes.len() + 1] {
+            let err = Png::from_bytes_at_offset(&bytes, wrong).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("no PNG signature at offset {}", wrong)
+            );
+        }
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_max_chunks() {
+        let bytes = testing_png().as_bytes();
+        let options = |max_chunks| ParseOptions {
+            max_chunks,
+            ..ParseOptions::default()
+        };
+        assert!(Png::try_from_with_options(&bytes, &options(3)).is_ok());
+        let err = Png::try_from_with_options(&bytes, &options(2)).unwrap_err();
+        assert_eq!(err.to_string(), "file has more than 2 chunks");
+    }
+
+    #[test]
+    fn test_stop_at_iend() {
+        let mut image = testing_png();
+        image.finalize().unwrap();
+        let mut bytes = image.as_bytes();
+        let len = bytes.len();
+        bytes.extend_from_slice(b"trailing");
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+        let options = ParseOptions {
+            stop_at_iend: true,
+            ..ParseOptions::default()
+        };
+        let parsed = Png::try_from_with_options(&bytes, &options).unwrap();
+        assert_eq!(parsed.as_bytes().len(), len);
+    }
+
+    #[test]
+    fn test_default_max_chunks() {
+        let iend = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        let mut bytes = Png::STANDARD_HEADER.to_vec();
+        for _ in 0..=ParseOptions::DEFAULT_MAX_CHUNKS {
+            bytes.extend(iend.as_bytes());
+        }
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+        assert!(Png::from_bytes_lenient(&bytes).is_err());
+        assert!(Png::from_reader(bytes.as_slice()).is_err());
+        bytes.truncate(bytes.len() - 12);
+        assert!(Png::try_from(bytes.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_smallest_inputs() {
+        // A bare signature is a PNG with no chunks.
+        let png = Png::try_from(&Png::STANDARD_HEADER[..]).unwrap();
+        assert_eq!(png.chunk_count(), 0);
+
+        // One empty chunk is exactly 12 bytes; one byte short must not parse.
+        let iend = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        let mut bytes = Png::STANDARD_HEADER.to_vec();
+        bytes.extend(iend.as_bytes());
+        assert_eq!(Png::try_from(bytes.as_slice()).unwrap().chunk_count(), 1);
+        assert_eq!(Png::from_reader(bytes.as_slice()).unwrap().chunk_count(), 1);
+        bytes.pop();
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+        assert!(Png::from_reader(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_empty_and_tiny_input() {
+        for bytes in [&[][..], &[137, 80, 78][..]] {
+            let err = Png::try_from(bytes).unwrap_err();
+            assert_eq!(err.to_string(), TOO_SMALL);
+            let err = Png::from_reader(bytes).unwrap_err();
+            assert!(err.to_string().contains(TOO_SMALL));
+        }
+    }
+
     #[test]
     fn test_invalid_header() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -263,6 +1110,69 @@ mod tests {
         assert_eq!(&chunk.data_as_string().unwrap(), "I am the first chunk");
     }
 
+    #[test]
+    fn test_chunk_by_type_invalid_type() {
+        let png = testing_png();
+        assert!(png.chunk_by_type("Fr1t").is_none());
+        assert!(png.chunk_by_type("FrStX").is_none());
+    }
+
+    #[test]
+    fn test_chunk_by_type_mut() {
+        let mut png = testing_png();
+        let chunk = png.chunk_by_type_mut("miDl").unwrap();
+        chunk.set_chunk_type(ChunkType::from_str("MiDl").unwrap());
+        assert!(png.chunk_by_type("miDl").is_none());
+        assert_eq!(png.chunks()[1].chunk_type_str(), Some("MiDl"));
+    }
+
+    #[test]
+    fn test_chunk_count() {
+        let mut png = testing_png();
+        assert_eq!(png.chunk_count(), 3);
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
+        assert_eq!(png.chunk_count(), 4);
+        assert_eq!(Png::try_from(&PNG_FILE[..]).unwrap().chunk_count(), 7);
+    }
+
+    #[test]
+    fn test_chunks_mut() {
+        let mut png = testing_png();
+        for chunk in png.chunks_mut() {
+            let upper = chunk.data().to_ascii_uppercase();
+            chunk.set_data(upper).unwrap();
+        }
+        let reparsed = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(
+            reparsed.chunks()[1].data_as_string().unwrap(),
+            "I AM ANOTHER CHUNK"
+        );
+        assert_eq!(reparsed.chunks(), png.chunks());
+    }
+
+    #[test]
+    fn test_index_of_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "Second middle").unwrap());
+        assert_eq!(png.index_of_type("FrSt"), Some(0));
+        assert_eq!(png.index_of_type("miDl"), Some(1));
+        assert_eq!(png.index_of_type("TeSt"), None);
+        assert_eq!(png.index_of_type("miDlX"), None);
+    }
+
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a second middle chunk").unwrap());
+        let chunks = png.chunks_by_type("miDl");
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            &chunks[1].data_as_string().unwrap(),
+            "I am a second middle chunk"
+        );
+        assert!(png.chunks_by_type("TeSt").is_empty());
+    }
+
     #[test]
     fn test_append_chunk() {
         let mut png = testing_png();
@@ -281,6 +1191,300 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_replace_chunk() {
+        let mut png = testing_png();
+        let old = png
+            .replace_chunk(1, chunk_from_strings("TeSt", "Replacement").unwrap())
+            .unwrap();
+        assert_eq!(old.chunk_type_str(), Some("miDl"));
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(png.chunks()[1].chunk_type_str(), Some("TeSt"));
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "Replacement");
+    }
+
+    #[test]
+    fn test_replace_chunk_out_of_range() {
+        let mut png = testing_png();
+        let result = png.replace_chunk(3, chunk_from_strings("TeSt", "Replacement").unwrap());
+        assert!(result.is_err());
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_remove_where() {
+        let mut png = png_of_types(&["IHDR", "tEXt", "IDAT", "ruSt", "IEND"]);
+        let removed = png.remove_where(|c| !c.chunk_type().is_critical());
+        let removed: Vec<&str> = removed
+            .iter()
+            .map(|c| c.chunk_type_str().unwrap())
+            .collect();
+        assert_eq!(removed, ["tEXt", "ruSt"]);
+        assert_eq!(type_list(&png), ["IHDR", "IDAT", "IEND"]);
+        assert!(png.remove_where(|_| false).is_empty());
+    }
+
+    #[test]
+    fn test_solid_gray() {
+        let png = Png::solid_gray(3, 2, 0x80).unwrap();
+        assert_eq!(type_list(&png), ["IHDR", "IDAT", "IEND"]);
+        let header = ImageHeader::try_from(&png.chunks()[0]).unwrap();
+        assert_eq!((header.width, header.height), (3, 2));
+        assert_eq!((header.bit_depth, header.color_type), (8, 0));
+
+        let pixels = [0, 0x80, 0x80, 0x80, 0, 0x80, 0x80, 0x80];
+        let mut idat = vec![0x78, 0x01, 1, 8, 0, 0xF7, 0xFF];
+        idat.extend_from_slice(&pixels);
+        idat.extend_from_slice(&adler32(&pixels).to_be_bytes());
+        assert_eq!(png.chunks()[1].data(), idat.as_slice());
+
+        let bytes = png.as_bytes();
+        assert!(Png::try_from(bytes.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_solid_gray_rejects_bad_sizes() {
+        assert!(Png::solid_gray(0, 1, 0).is_err());
+        assert!(Png::solid_gray(1, 0, 0).is_err());
+        assert!(Png::solid_gray(u32::MAX, u32::MAX, 0).is_err());
+    }
+
+    #[test]
+    fn test_zlib_stored_splits_blocks() {
+        let data = vec![7; MAX_STORED_BLOCK + 10];
+        let stream = zlib_stored(&data);
+        assert_eq!(stream.len(), 2 + 5 + MAX_STORED_BLOCK + 5 + 10 + 4);
+        // The first block isn't final and holds a full 65535 bytes.
+        assert_eq!(&stream[2..7], &[0, 0xFF, 0xFF, 0, 0]);
+        let second = 7 + MAX_STORED_BLOCK;
+        assert_eq!(&stream[second..second + 5], &[1, 10, 0, 0xF5, 0xFF]);
+
+        assert_eq!(
+            zlib_stored(&[]),
+            [0x78, 0x01, 1, 0, 0, 0xFF, 0xFF, 0, 0, 0, 1]
+        );
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    #[test]
+    fn test_encode_message_round_trip() {
+        let binary: Vec<u8> = (0..=255).collect();
+        let messages: [&[u8]; 4] = [
+            b"hidden message",
+            &binary,
+            b"",
+            "caf\u{e9} \u{1f980} \u{65e5}\u{672c}".as_bytes(),
+        ];
+        for message in messages {
+            let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+            png.encode_message("ruSt", message).unwrap();
+            let reparsed = Png::try_from(png.as_bytes().as_slice()).unwrap();
+            assert_eq!(reparsed.chunks_by_type("ruSt").len(), 1);
+            assert_eq!(reparsed.data_of_type("ruSt"), message);
+            assert!(reparsed
+                .chunks()
+                .last()
+                .unwrap()
+                .chunk_type()
+                .matches("IEND"));
+        }
+    }
+
+    #[test]
+    fn test_encode_message_bad_type() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(png.encode_message("ru5t", b"x").is_err());
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_scan_headers_matches_full_parse() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let headers = Png::scan_headers(io::Cursor::new(&PNG_FILE[..])).unwrap();
+        assert_eq!(headers.len(), png.chunks().len());
+        let mut offset = 8;
+        for (header, chunk) in headers.iter().zip(png.chunks()) {
+            assert_eq!(header.offset, offset);
+            assert_eq!(header.length, chunk.length());
+            assert_eq!(&header.chunk_type, chunk.chunk_type());
+            offset += 12 + chunk.length() as u64;
+        }
+    }
+
+    #[test]
+    fn test_scan_headers_from_current_position() {
+        let mut bytes = b"junk".to_vec();
+        bytes.extend_from_slice(&PNG_FILE);
+        let mut cursor = io::Cursor::new(bytes);
+        cursor.seek(SeekFrom::Start(4)).unwrap();
+        let headers = Png::scan_headers(cursor).unwrap();
+        assert_eq!(headers[0].offset, 8);
+        assert!(headers[0].chunk_type.matches("IHDR"));
+    }
+
+    #[test]
+    fn test_scan_headers_truncated() {
+        for end in [0, 7, 12, PNG_FILE.len() - 1] {
+            let result = Png::scan_headers(io::Cursor::new(&PNG_FILE[..end]));
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_insert_around_type() {
+        let mut png = png_of_types(&["IHDR", "IDAT", "IEND"]);
+        let new_chunk = |t| chunk_from_strings(t, "").unwrap();
+
+        assert_eq!(png.insert_after_type("IHDR", new_chunk("tEXt")).unwrap(), 1);
+        assert_eq!(
+            png.insert_before_type("IHDR", new_chunk("prEv")).unwrap(),
+            0
+        );
+        assert_eq!(
+            png.insert_before_type("IDAT", new_chunk("gAMA")).unwrap(),
+            3
+        );
+        assert_eq!(png.insert_after_type("IDAT", new_chunk("ruSt")).unwrap(), 5);
+        assert_eq!(
+            type_list(&png),
+            ["prEv", "IHDR", "tEXt", "gAMA", "IDAT", "ruSt", "IEND"]
+        );
+
+        let err = png
+            .insert_after_type("PLTE", new_chunk("ruSt"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no PLTE chunk to insert next to");
+        assert!(png.insert_before_type("zTXt", new_chunk("ruSt")).is_err());
+        assert_eq!(png.chunks().len(), 7);
+    }
+
+    #[test]
+    fn test_insert_around_type_uses_first_anchor() {
+        let mut png = png_of_types(&["IHDR", "tEXt", "tEXt", "IDAT", "IDAT", "IEND"]);
+        let new_chunk = |t| chunk_from_strings(t, "").unwrap();
+
+        assert_eq!(png.insert_after_type("tEXt", new_chunk("ruSt")).unwrap(), 2);
+        assert_eq!(png.insert_after_type("IDAT", new_chunk("prVt")).unwrap(), 5);
+        assert_eq!(
+            png.insert_before_type("tEXt", new_chunk("gAMA")).unwrap(),
+            1
+        );
+        assert_eq!(
+            type_list(&png),
+            ["IHDR", "gAMA", "tEXt", "ruSt", "tEXt", "IDAT", "prVt", "IDAT", "IEND"]
+        );
+    }
+
+    #[test]
+    fn test_retain_chunks_by_size() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "0123456789abc").unwrap(),
+            chunk_from_strings("tEXt", "short").unwrap(),
+            chunk_from_strings("IDAT", "pixels").unwrap(),
+            chunk_from_strings("ruSt", "a much longer payload").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.retain_chunks(|c| c.length() <= 10);
+        assert_eq!(type_list(&png), ["IHDR", "tEXt", "IDAT", "IEND"]);
+
+        png.retain_chunks(|_| false);
+        assert_eq!(type_list(&png), ["IHDR", "IEND"]);
+    }
+
+    #[test]
+    fn test_unchecked_type_does_not_panic() {
+        let odd = ChunkType::from_bytes_unchecked([0, 0xff, 0x10, 0xab]);
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "0123456789abc").unwrap(),
+            Chunk::new(odd, b"odd".to_vec()),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        assert!(png.validate_lengths().is_ok());
+        png.canonicalize();
+        png.retain_chunks(|c| c.chunk_type_str().is_some());
+        assert_eq!(type_list(&png), ["IHDR", "IEND"]);
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("TeSt", "x").unwrap())
+            .unwrap();
+        png.insert_chunk(4, chunk_from_strings("EnDs", "y").unwrap())
+            .unwrap();
+        let types: Vec<&str> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type_str().unwrap())
+            .collect();
+        assert_eq!(types, ["FrSt", "TeSt", "miDl", "LASt", "EnDs"]);
+        assert!(png
+            .insert_chunk(6, chunk_from_strings("TeSt", "z").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_remove_chunk_at() {
+        let mut png = testing_png();
+        let removed = png.remove_chunk_at(1).unwrap();
+        assert_eq!(removed.chunk_type_str(), Some("miDl"));
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunks()[1].chunk_type_str(), Some("LASt"));
+    }
+
+    #[test]
+    fn test_remove_chunk_at_out_of_range() {
+        let mut png = testing_png();
+        assert!(png.remove_chunk_at(3).is_err());
+        assert_eq!(png.chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_from_bytes_lenient() {
+        let mut chunks = testing_chunks();
+        chunks[1] =
+            Chunk::new_unchecked(chunks[1].chunk_type().clone(), b"hidden".to_vec(), 0).unwrap();
+        let bytes = Png::from_chunks(chunks).as_bytes();
+
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+        let png = Png::from_bytes_lenient(bytes.as_slice()).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert!(!png.chunks()[1].crc_is_valid());
+        assert_eq!(png.chunks()[1].data(), b"hidden");
+        assert!(Png::from_bytes_lenient(&bytes[..12]).is_err());
+    }
+
+    #[test]
+    fn test_merge_ancillary_from() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("tEXt", "mine").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        let other = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "other header").unwrap(),
+            chunk_from_strings("tEXt", "theirs").unwrap(),
+            chunk_from_strings("IDAT", "pixels").unwrap(),
+            chunk_from_strings("ruSt", "hidden").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        png.merge_ancillary_from(&other);
+
+        let types: Vec<&str> = png
+            .chunks()
+            .iter()
+            .map(|c| c.chunk_type_str().unwrap())
+            .collect();
+        assert_eq!(types, ["IHDR", "tEXt", "tEXt", "ruSt", "IEND"]);
+        assert_eq!(png.chunks()[2].data(), b"theirs");
+        assert_eq!(png.chunks_by_type("IHDR").len(), 1);
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
@@ -295,6 +1499,127 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_data_bytes_by_type() {
+        let png = Png::from_chunks(vec![
+            chunk_from_strings("IDAT", "12345").unwrap(),
+            chunk_from_strings("ruSt", "abc").unwrap(),
+            chunk_from_strings("IDAT", "678").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        let totals = png.data_bytes_by_type();
+        let total = |t: &str| totals[&ChunkType::from_str(t).unwrap()];
+        assert_eq!(totals.len(), 3);
+        assert_eq!(total("IDAT"), 8);
+        assert_eq!(total("ruSt"), 3);
+        assert_eq!(total("IEND"), 0);
+    }
+
+    #[test]
+    fn test_data_of_type() {
+        let png = Png::from_chunks(vec![
+            chunk_from_strings("ruSt", "hidden ").unwrap(),
+            chunk_from_strings("IDAT", "xyz").unwrap(),
+            chunk_from_strings("ruSt", "message").unwrap(),
+        ]);
+        assert_eq!(png.data_of_type("ruSt"), b"hidden message");
+        assert_eq!(png.data_of_type("IDAT"), b"xyz");
+        assert!(png.data_of_type("tEXt").is_empty());
+    }
+
+    #[test]
+    fn test_chunk_counts_by_type() {
+        let png = png_of_types(&["IHDR", "IDAT", "ruSt", "IDAT", "IEND"]);
+        let counts = png.chunk_counts_by_type();
+        let count = |t: &str| counts[&ChunkType::from_str(t).unwrap()];
+        assert_eq!(counts.len(), 4);
+        assert_eq!(count("IDAT"), 2);
+        assert_eq!(count("ruSt"), 1);
+    }
+
+    #[test]
+    fn test_image_header() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let header = png.image_header().unwrap();
+        assert_eq!(header.width, 50);
+        assert_eq!(header.height, 50);
+        assert_eq!(header.color_type_name(), "RGBA");
+    }
+
+    #[test]
+    fn test_image_header_missing() {
+        let png = testing_png();
+        assert!(png.image_header().is_err());
+    }
+
+    #[test]
+    fn test_last_modified() {
+        let png = testing_png();
+        assert_eq!(png.last_modified().unwrap(), None);
+
+        let mut png = testing_png();
+        let time = [0x07, 0xea, 10, 14, 9, 5, 30];
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("tIME").unwrap(),
+            time.to_vec(),
+        ));
+        let modified = png.last_modified().unwrap().unwrap();
+        assert_eq!(modified.to_string(), "2026-10-14 09:05:30 UTC");
+
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("tIME", "bad").unwrap());
+        assert!(png.last_modified().is_err());
+    }
+
+    #[test]
+    fn test_is_animated() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert!(!png.is_animated());
+        png.append_chunk(Chunk::new(ChunkType::from_str("acTL").unwrap(), vec![0; 8]));
+        assert!(png.is_animated());
+    }
+
+    #[test]
+    fn test_finalize_appends_iend() {
+        let mut png = testing_png();
+        png.finalize().unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.chunks()[3].chunk_type_str(), Some("IEND"));
+        assert_eq!(png.chunks()[3].length(), 0);
+    }
+
+    #[test]
+    fn test_finalize_moves_iend_to_end() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("ruSt", "after the end").unwrap());
+        png.finalize().unwrap();
+        let chunks = png.chunks();
+        assert_eq!(chunks[chunks.len() - 2].chunk_type_str(), Some("ruSt"));
+        assert_eq!(chunks[chunks.len() - 1].chunk_type_str(), Some("IEND"));
+        assert_eq!(png.chunks_by_type("IEND").len(), 1);
+    }
+
+    #[test]
+    fn test_finalize_is_idempotent() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.finalize().unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+    }
+
+    #[test]
+    fn test_finalize_multiple_iend() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.append_chunk(chunk_from_strings("IEND", "").unwrap());
+        assert!(png.finalize().is_err());
+    }
+
+    #[test]
+    fn test_finalize_non_empty_iend() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("IEND", "data").unwrap());
+        assert!(png.finalize().is_err());
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -559,4 +1884,170 @@ mod tests {
         202, 28, 31, 66, 176, 235, 16, 0, 0, 0, 3, 82, 117, 83, 116, 104, 101, 121, 158, 176, 245,
         160, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
     ];
+
+    fn png_of_types(types: &[&str]) -> Png {
+        Png::from_chunks(
+            types
+                .iter()
+                .map(|t| chunk_from_strings(t, "").unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_validate_order_ok() {
+        let png = png_of_types(&["IHDR", "PLTE", "tEXt", "IDAT", "IDAT", "tEXt", "IEND"]);
+        assert_eq!(png.validate_order(), Ok(()));
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.validate_order(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_order_ihdr_not_first() {
+        let png = png_of_types(&["tEXt", "IHDR", "IDAT", "IEND"]);
+        assert_eq!(png.validate_order(), Err(vec![OrderError::IhdrNotFirst]));
+    }
+
+    #[test]
+    fn test_validate_order_iend_not_last() {
+        let png = png_of_types(&["IHDR", "IDAT", "IEND", "ruSt"]);
+        assert_eq!(png.validate_order(), Err(vec![OrderError::IendNotLast]));
+    }
+
+    #[test]
+    fn test_validate_order_plte_after_idat() {
+        let png = png_of_types(&["IHDR", "IDAT", "PLTE", "IEND"]);
+        assert_eq!(
+            png.validate_order(),
+            Err(vec![OrderError::PlteAfterIdat { index: 2 }])
+        );
+    }
+
+    #[test]
+    fn test_validate_order_idat_not_contiguous() {
+        let png = png_of_types(&["IHDR", "IDAT", "ruSt", "IDAT", "IEND"]);
+        assert_eq!(
+            png.validate_order(),
+            Err(vec![OrderError::IdatNotContiguous { first: 1, last: 3 }])
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let png = testing_png();
+        // Same types and lengths, different data.
+        let zeroed = png_of_lengths(&[("FrSt", 20), ("miDl", 18), ("LASt", 19)]);
+        assert_eq!(png.fingerprint(), zeroed.fingerprint());
+
+        let resized = png_of_lengths(&[("FrSt", 20), ("miDl", 18), ("LASt", 18)]);
+        assert_ne!(png.fingerprint(), resized.fingerprint());
+
+        let mut other = testing_png();
+        other.append_chunk(chunk_from_strings("ruSt", "").unwrap());
+        assert_ne!(png.fingerprint(), other.fingerprint());
+
+        let mut swapped = testing_png();
+        swapped.chunks_mut().swap(0, 1);
+        assert_ne!(png.fingerprint(), swapped.fingerprint());
+    }
+
+    fn type_list(png: &Png) -> Vec<&str> {
+        png.chunks()
+            .iter()
+            .map(|c| c.chunk_type_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut png = png_of_types(&[
+            "tEXt", "IHDR", "IDAT", "ruSt", "IDAT", "PLTE", "IEND", "zTXt",
+        ]);
+        png.canonicalize();
+        assert_eq!(
+            type_list(&png),
+            ["IHDR", "tEXt", "PLTE", "IDAT", "IDAT", "ruSt", "zTXt", "IEND"]
+        );
+        assert_eq!(png.validate_order(), Ok(()));
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_data_and_valid_order() {
+        let mut png = Png::try_from(&PNG_FILE[..]).unwrap();
+        png.canonicalize();
+        assert_eq!(png.as_bytes(), PNG_FILE.to_vec());
+
+        let mut png = png_of_types(&["ruSt", "IEND", "IHDR"]);
+        png.canonicalize();
+        assert_eq!(type_list(&png), ["IHDR", "ruSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_validate_order_reports_every_violation() {
+        let errors = Png::from_chunks(Vec::new()).validate_order().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![OrderError::IhdrNotFirst, OrderError::IendNotLast]
+        );
+    }
+
+    fn png_of_lengths(chunks: &[(&str, usize)]) -> Png {
+        Png::from_chunks(
+            chunks
+                .iter()
+                .map(|(t, len)| Chunk::new(ChunkType::from_str(t).unwrap(), vec![0; *len]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_validate_lengths_ok() {
+        let png = png_of_lengths(&[("IHDR", 13), ("PLTE", 6), ("IDAT", 0), ("IEND", 0)]);
+        assert_eq!(png.validate_lengths(), Ok(()));
+        assert_eq!(
+            Png::try_from(&PNG_FILE[..]).unwrap().validate_lengths(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_lengths_ihdr() {
+        let errors = png_of_lengths(&[("IHDR", 0), ("IEND", 0)])
+            .validate_lengths()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "IHDR at index 0 has 0 bytes, expected 13 bytes"
+        );
+    }
+
+    #[test]
+    fn test_validate_lengths_iend() {
+        let errors = png_of_lengths(&[("IHDR", 13), ("IEND", 4)])
+            .validate_lengths()
+            .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].index, errors[0].found), (1, 4));
+    }
+
+    #[test]
+    fn test_validate_lengths_plte() {
+        for bad in [0, 4, 771] {
+            let errors = png_of_lengths(&[("IHDR", 13), ("PLTE", bad), ("IEND", 0)])
+                .validate_lengths()
+                .unwrap_err();
+            assert_eq!(errors.len(), 1, "PLTE of {} bytes", bad);
+            assert!(errors[0].chunk_type.matches("PLTE"));
+        }
+    }
+
+    #[test]
+    fn test_validate_lengths_reports_every_violation() {
+        let errors = png_of_lengths(&[("IHDR", 12), ("PLTE", 2), ("IEND", 1)])
+            .validate_lengths()
+            .unwrap_err();
+        let indices: Vec<usize> = errors.iter().map(|e| e.index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+    }
 }
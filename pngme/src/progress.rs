@@ -0,0 +1,121 @@
+use std::io::{self, Read, Write};
+
+/// Tracks bytes processed against a known total and prints a percentage to stderr
+/// whenever it changes. Finishes the line once the total is reached.
+struct Progress {
+    label: String,
+    total: u64,
+    done: u64,
+    last_percent: Option<u64>,
+}
+
+impl Progress {
+    fn new(label: &str, total: u64) -> Self {
+        Progress {
+            label: label.to_owned(),
+            total,
+            done: 0,
+            last_percent: None,
+        }
+    }
+
+    fn advance(&mut self, bytes: usize) {
+        self.done += bytes as u64;
+        let percent = percent(self.done, self.total);
+        if self.last_percent == Some(percent) {
+            return;
+        }
+        self.last_percent = Some(percent);
+        eprint!("\r{}: {}%", self.label, percent);
+        if percent == 100 {
+            eprintln!();
+        }
+    }
+}
+
+fn percent(done: u64, total: u64) -> u64 {
+    if total == 0 {
+        return 100;
+    }
+    (done.min(total) * 100) / total
+}
+
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Progress,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, label: &str, total: u64) -> Self {
+        ProgressReader {
+            inner,
+            progress: Progress::new(label, total),
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.advance(n);
+        Ok(n)
+    }
+}
+
+pub struct ProgressWriter<W> {
+    inner: W,
+    progress: Progress,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    pub fn new(inner: W, label: &str, total: u64) -> Self {
+        ProgressWriter {
+            inner,
+            progress: Progress::new(label, total),
+        }
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.progress.advance(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 200), 0);
+        assert_eq!(percent(50, 200), 25);
+        assert_eq!(percent(200, 200), 100);
+        assert_eq!(percent(0, 0), 100);
+    }
+
+    #[test]
+    fn test_progress_reader_passes_bytes_through() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut reader = ProgressReader::new(data.as_slice(), "test", data.len() as u64);
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, data);
+        assert_eq!(reader.progress.done, 256);
+    }
+
+    #[test]
+    fn test_progress_writer_passes_bytes_through() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut writer = ProgressWriter::new(Vec::new(), "test", data.len() as u64);
+        writer.write_all(&data).unwrap();
+        assert_eq!(writer.inner, data);
+        assert_eq!(writer.progress.last_percent, Some(100));
+    }
+}
@@ -0,0 +1,186 @@
+use flate2::read::ZlibDecoder;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::Read;
+
+/// The contents of a `tEXt` chunk: a Latin-1 keyword, a null separator, then the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    pub keyword: String,
+    /// The text exactly as stored, so callers can pick how to decode it.
+    pub text: Vec<u8>,
+}
+
+impl TextChunk {
+    pub const MAX_KEYWORD_LEN: usize = 79;
+
+    pub fn new(keyword: &str, text: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        validate_keyword(keyword)?;
+        Ok(TextChunk {
+            keyword: keyword.to_string(),
+            text,
+        })
+    }
+
+    /// Serializes as chunk data: keyword bytes, `0`, text bytes.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        // validate_keyword guarantees every char fits in one Latin-1 byte.
+        let mut bytes: Vec<u8> = self.keyword.chars().map(|c| c as u8).collect();
+        bytes.push(0);
+        bytes.extend_from_slice(&self.text);
+        bytes
+    }
+}
+
+/// Keywords are 1 to 79 printable Latin-1 characters.
+fn validate_keyword(keyword: &str) -> Result<(), Box<dyn Error>> {
+    let len = keyword.chars().count();
+    if len == 0 || len > TextChunk::MAX_KEYWORD_LEN {
+        return Err(format!(
+            "tEXt keyword must be 1 to {} bytes, found {}",
+            TextChunk::MAX_KEYWORD_LEN,
+            len
+        )
+        .into());
+    }
+    if let Some(c) = keyword
+        .chars()
+        .find(|&c| !matches!(c as u32, 32..=126 | 161..=255))
+    {
+        return Err(format!("tEXt keyword contains invalid character {:?}", c).into());
+    }
+    Ok(())
+}
+
+impl TryFrom<&[u8]> for TextChunk {
+    type Error = Box<dyn Error>;
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let separator = data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or("malformed tEXt: no null separator after keyword")?;
+        let keyword: String = data[..separator].iter().map(|&b| b as char).collect();
+        TextChunk::new(&keyword, data[separator + 1..].to_vec())
+    }
+}
+
+/// The contents of a `zTXt` chunk: a keyword and null separator as in `tEXt`, then a
+/// compression method byte and the zlib-compressed text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedTextChunk {
+    pub keyword: String,
+    /// The text after inflating, undecoded like [`TextChunk::text`].
+    pub text: Vec<u8>,
+}
+
+impl CompressedTextChunk {
+    /// Method 0, zlib, is the only compression method PNG defines.
+    pub const ZLIB: u8 = 0;
+    /// Longest text this will inflate, so a small crafted chunk can't expand to fill
+    /// memory.
+    pub const MAX_TEXT_LEN: u64 = 16 * 1024 * 1024;
+}
+
+impl TryFrom<&[u8]> for CompressedTextChunk {
+    type Error = Box<dyn Error>;
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let TextChunk { keyword, text } = TextChunk::try_from(data)?;
+        let (&method, compressed) = text
+            .split_first()
+            .ok_or("malformed zTXt: no compression method after keyword")?;
+        if method != Self::ZLIB {
+            return Err(format!("malformed zTXt: unknown compression method {}", method).into());
+        }
+        let mut inflated = Vec::new();
+        ZlibDecoder::new(compressed)
+            .take(Self::MAX_TEXT_LEN + 1)
+            .read_to_end(&mut inflated)
+            .map_err(|e| format!("malformed zTXt: {}", e))?;
+        if inflated.len() as u64 > Self::MAX_TEXT_LEN {
+            return Err(format!(
+                "zTXt text inflates to more than {} bytes",
+                Self::MAX_TEXT_LEN
+            )
+            .into());
+        }
+        Ok(CompressedTextChunk {
+            keyword,
+            text: inflated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = TextChunk::new("Comment", b"Hello from pngme".to_vec()).unwrap();
+        let bytes = text.as_bytes();
+        assert_eq!(bytes, b"Comment\0Hello from pngme");
+        assert_eq!(TextChunk::try_from(bytes.as_slice()).unwrap(), text);
+    }
+
+    #[test]
+    fn test_splits_on_first_null() {
+        let text = TextChunk::try_from(&b"Title\0a\0b"[..]).unwrap();
+        assert_eq!(text.keyword, "Title");
+        assert_eq!(text.text, b"a\0b");
+    }
+
+    #[test]
+    fn test_keyword_length() {
+        assert!(TextChunk::new("", Vec::new()).is_err());
+        assert!(TextChunk::new(&"k".repeat(79), Vec::new()).is_ok());
+        assert!(TextChunk::new(&"k".repeat(80), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_keyword_characters() {
+        assert!(TextChunk::new("Caf\u{e9}", Vec::new()).is_ok());
+        assert!(TextChunk::new("Tab\tbed", Vec::new()).is_err());
+        assert!(TextChunk::new("\u{263a}", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_missing_separator() {
+        assert!(TextChunk::try_from(&b"no separator"[..]).is_err());
+    }
+
+    /// "compressed hello, hello, hello!" as zlib output at level 9.
+    const COMPRESSED: &[u8] = b"\x78\xda\x4b\xce\xcf\x2d\x28\x4a\x2d\x2e\x4e\x4d\x51\xc8\x48\xcd\
+        \xc9\xc9\xd7\x41\xa1\x14\x01\xbd\x57\x0b\x4b";
+
+    fn ztxt_data(keyword: &str, method: u8, compressed: &[u8]) -> Vec<u8> {
+        let mut data = keyword.as_bytes().to_vec();
+        data.push(0);
+        data.push(method);
+        data.extend_from_slice(compressed);
+        data
+    }
+
+    #[test]
+    fn test_compressed_text() {
+        let data = ztxt_data("Comment", 0, COMPRESSED);
+        let text = CompressedTextChunk::try_from(data.as_slice()).unwrap();
+        assert_eq!(text.keyword, "Comment");
+        assert_eq!(text.text, b"compressed hello, hello, hello!");
+    }
+
+    #[test]
+    fn test_compressed_text_malformed() {
+        let parse = |data: &[u8]| CompressedTextChunk::try_from(data).map_err(|e| e.to_string());
+        assert!(parse(b"Comment\0")
+            .unwrap_err()
+            .contains("no compression method"));
+        let unknown_method = ztxt_data("Comment", 1, COMPRESSED);
+        assert!(parse(&unknown_method)
+            .unwrap_err()
+            .contains("compression method 1"));
+        let truncated = ztxt_data("Comment", 0, &COMPRESSED[..10]);
+        assert!(parse(&truncated).unwrap_err().starts_with("malformed zTXt"));
+        let not_zlib = ztxt_data("Comment", 0, b"plain text");
+        assert!(parse(&not_zlib).unwrap_err().starts_with("malformed zTXt"));
+    }
+}
@@ -0,0 +1,1455 @@
+use pngme::chunk::Chunk;
+use pngme::png::Png;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// A file in a per-test scratch directory, which is deleted again when this drops.
+/// Derefs to the file's path.
+struct Scratch {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl Scratch {
+    fn new(test_name: &str, file_name: &str) -> Scratch {
+        let dir = std::env::temp_dir().join(format!("pngme-{}-{}", std::process::id(), test_name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(file_name);
+        Scratch { dir, path }
+    }
+}
+
+impl std::ops::Deref for Scratch {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for Scratch {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Copies a fixture into a fresh scratch location so commands that write in place
+/// never touch the checked-in file.
+fn scratch_copy(name: &str, test_name: &str) -> Scratch {
+    let scratch = Scratch::new(test_name, name);
+    fs::copy(fixture(name), &scratch).unwrap();
+    scratch
+}
+
+fn pngme(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pngme"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout_of(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_fixtures_parse() {
+    for name in ["minimal.png", "animated.png", "text.png"] {
+        assert!(
+            Png::from_file(fixture(name)).is_ok(),
+            "{} did not parse",
+            name
+        );
+    }
+}
+
+/// minimal.png is the smallest useful PNG: a 1x1 grayscale image with one IDAT.
+#[test]
+fn test_read_only_commands_on_1x1_png() {
+    let file = fixture("minimal.png");
+    let file = file.to_str().unwrap();
+    let runs: [&[&str]; 11] = [
+        &["print", file],
+        &["print", file, "--hexdump"],
+        &["info", file],
+        &["stats", file],
+        &["fingerprint", file],
+        &["checksum", file],
+        &["verify", file],
+        &["list-keywords", file],
+        &["capacity", file],
+        &["scan", file],
+        &["find", file, "x"],
+    ];
+    for args in runs {
+        let output = pngme(args);
+        assert!(output.status.success(), "{:?} failed: {:?}", args, output);
+    }
+    let stdout = stdout_of(&pngme(&["info", file]));
+    assert!(stdout.contains("Width: 1\nHeight: 1\n"), "{}", stdout);
+}
+
+/// Parsing and re-serializing must reproduce each fixture exactly: any drift in the
+/// signature, chunk order or trailing bytes shows up here first.
+#[test]
+fn test_fixtures_as_bytes_matches_file() {
+    for name in ["minimal.png", "animated.png", "text.png"] {
+        let original = fs::read(fixture(name)).unwrap();
+        let png = Png::try_from(original.as_slice()).unwrap();
+        assert!(
+            png.as_bytes() == original,
+            "{} changed after a parse/serialize round trip",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_new_writes_usable_png() {
+    let path = Scratch::new("new", "gray.png");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["new", path, "4", "3", "--gray", "0"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), format!("Wrote 4x3 image to {}\n", path));
+    assert!(pngme(&["verify", path]).status.success());
+    assert!(pngme(&["encode", path, "ruSt", "demo"]).status.success());
+    assert_eq!(stdout_of(&pngme(&["decode", path, "ruSt"])), "demo\n");
+
+    assert!(!pngme(&["new", path, "0", "3"]).status.success());
+}
+
+#[test]
+fn test_encode_decode_round_trip() {
+    let path = scratch_copy("minimal.png", "round_trip");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "ruSt", "hidden message"]);
+    assert!(output.status.success());
+
+    let output = pngme(&["decode", path, "ruSt"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "hidden message\n");
+}
+
+#[test]
+fn test_empty_message_round_trip() {
+    let path = scratch_copy("minimal.png", "empty_message");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruSt", ""]).status.success());
+    assert_eq!(Png::from_file(path).unwrap().data_of_type("ruSt"), b"");
+
+    let output = pngme(&["decode", path, "ruSt"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "\n");
+    let output = pngme(&["decode", path, "ruSt", "--output-format", "raw"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    assert!(pngme(&["encode", path, "spLt", "", "--split", "4"])
+        .status
+        .success());
+    assert_eq!(
+        stdout_of(&pngme(&["decode", path, "spLt", "--split"])),
+        "\n"
+    );
+}
+
+#[test]
+fn test_encode_keeps_existing_chunks() {
+    let path = scratch_copy("animated.png", "keeps_existing");
+    let before = Png::from_file(&path).unwrap().chunks().len();
+
+    let output = pngme(&["encode", path.to_str().unwrap(), "ruSt", "frame data"]);
+    assert!(output.status.success());
+
+    let png = Png::from_file(&path).unwrap();
+    assert_eq!(png.chunks().len(), before + 1);
+    assert!(png.is_animated());
+}
+
+#[test]
+fn test_encode_stdout_pipeline() {
+    let path = scratch_copy("minimal.png", "stdout_pipeline");
+    let path = path.to_str().unwrap();
+    let original = fs::read(path).unwrap();
+
+    let first = pngme(&["encode", path, "ruSt", "one", "--stdout"]);
+    assert!(first.status.success());
+    assert_eq!(fs::read(path).unwrap(), original);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pngme"))
+        .args(["encode", "-", "ruSt", "two", "--stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&first.stdout)
+        .unwrap();
+    let second = child.wait_with_output().unwrap();
+    assert!(second.status.success());
+
+    let png = Png::try_from(second.stdout.as_slice()).unwrap();
+    let payloads: Vec<&[u8]> = png
+        .chunks_by_type("ruSt")
+        .iter()
+        .map(|c| c.data())
+        .collect();
+    assert_eq!(payloads, vec![&b"one"[..], &b"two"[..]]);
+    assert!(png.chunks().last().unwrap().is_iend());
+}
+
+#[test]
+fn test_encode_warns_on_risky_types() {
+    let path = scratch_copy("minimal.png", "risky_types");
+    let path = path.to_str().unwrap();
+    let stderr = |output: Output| String::from_utf8_lossy(&output.stderr).into_owned();
+
+    let output = pngme(&["encode", path, "RuSt", "x"]);
+    assert!(output.status.success());
+    assert!(stderr(output).contains("critical"));
+    assert!(stderr(pngme(&["encode", path, "tIME", "x"])).contains("standard"));
+    assert!(stderr(pngme(&["encode", path, "RuSt", "x", "--force"])).is_empty());
+    assert!(stderr(pngme(&["encode", path, "ruSt", "x"])).is_empty());
+}
+
+#[test]
+fn test_encode_if_absent() {
+    let path = scratch_copy("minimal.png", "if_absent");
+    let path = path.to_str().unwrap();
+
+    assert!(pngme(&["encode", path, "ruSt", "first", "--if-absent"])
+        .status
+        .success());
+    let output = pngme(&["encode", path, "ruSt", "second", "--if-absent"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped"));
+    let output = pngme(&["encode", path, "ruSt", "third", "--if-absent=error"]);
+    assert!(!output.status.success());
+
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(png.chunks_by_type("ruSt").len(), 1);
+    assert_eq!(png.chunk_by_type("ruSt").unwrap().data(), b"first");
+}
+
+#[test]
+fn test_encode_timestamp_sets_single_time_chunk() {
+    let path = scratch_copy("minimal.png", "encode_timestamp");
+    for message in ["one", "two"] {
+        let output = pngme(&[
+            "encode",
+            path.to_str().unwrap(),
+            "ruSt",
+            message,
+            "--timestamp",
+        ]);
+        assert!(output.status.success());
+    }
+    let png = Png::from_file(&path).unwrap();
+    assert_eq!(png.chunks_by_type("tIME").len(), 1);
+    assert_eq!(png.chunks_by_type("tIME")[0].length(), 7);
+    assert!(png.chunks().last().unwrap().chunk_type().matches("IEND"));
+}
+
+#[test]
+fn test_preserve_mtime() {
+    let path = scratch_copy("animated.png", "preserve_mtime");
+    let old = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    let set_old = || {
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap()
+    };
+    let mtime = || fs::metadata(&path).unwrap().modified().unwrap();
+    let file = path.to_str().unwrap();
+
+    set_old();
+    assert!(pngme(&["encode", file, "ruSt", "x", "--preserve-mtime"])
+        .status
+        .success());
+    assert_eq!(mtime(), old);
+    assert!(pngme(&["remove", file, "ruSt", "--preserve-mtime"])
+        .status
+        .success());
+    assert_eq!(mtime(), old);
+    assert!(pngme(&["remove", file, "--ancillary"]).status.success());
+    assert_ne!(mtime(), old);
+}
+
+#[test]
+fn test_encode_type_hex_writes_exact_bytes() {
+    let path = scratch_copy("minimal.png", "type_hex");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "--type-hex", "31323334", "hi"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a valid chunk type"));
+
+    let bytes = fs::read(path).unwrap();
+    let start = bytes.len() - 12 - 14;
+    assert_eq!(&bytes[start..start + 10], b"\0\0\0\x021234hi");
+}
+
+#[test]
+fn test_encode_type_hex_cannot_be_read_back() {
+    let path = scratch_copy("minimal.png", "type_hex_read_back");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "--type-hex", "00ff10ab", "hi"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not be able to read it back"));
+
+    for args in [
+        &["list", path][..],
+        &["stats", path],
+        &["remove", path, "--index", "2"],
+    ] {
+        let output = pngme(args);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Bad bytes"));
+    }
+}
+
+#[test]
+fn test_encode_message_file_strips_crlf() {
+    let path = scratch_copy("minimal.png", "message_file");
+    let message_path = path.with_file_name("message.txt");
+    fs::write(&message_path, "first line\r\nsecond line\r\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&[
+        "encode",
+        path,
+        "ruSt",
+        "--message-file",
+        message_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(
+        png.chunk_by_type("ruSt").unwrap().data(),
+        b"first line\nsecond line\n"
+    );
+}
+
+#[test]
+fn test_encode_message_file_conflicts_with_message() {
+    let path = scratch_copy("minimal.png", "message_file_conflict");
+    let message_path = path.with_file_name("conflict_message.txt");
+    fs::write(&message_path, "from file").unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&[
+        "encode",
+        path,
+        "ruSt",
+        "positional",
+        "--message-file",
+        message_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not both"));
+    assert!(Png::from_file(path)
+        .unwrap()
+        .chunk_by_type("ruSt")
+        .is_none());
+}
+
+#[test]
+fn test_encode_pad_to_round_trip() {
+    let path = scratch_copy("minimal.png", "pad_to");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "ruSt", "record", "--pad-to", "16"]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(png.chunk_by_type("ruSt").unwrap().length(), 16);
+
+    let output = pngme(&["decode", path, "ruSt", "--unpad"]);
+    assert_eq!(stdout_of(&output), "record\n");
+}
+
+#[test]
+fn test_decode_raw_output() {
+    let path = fixture("text.png");
+    let output = pngme(&[
+        "decode",
+        path.to_str().unwrap(),
+        "tEXt",
+        "--output-format",
+        "raw",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Comment\0Hello from pngme");
+}
+
+#[test]
+fn test_decode_auto_falls_back_to_hex() {
+    let path = scratch_copy("minimal.png", "decode_auto");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruSt", "abc"]).status.success());
+
+    let output = pngme(&["decode", path, "ruSt", "--output-format", "auto"]);
+    assert_eq!(stdout_of(&output), "abc\n");
+
+    // Three bytes can't be UTF-16, so auto shows them as hex instead of failing.
+    let args = ["decode", path, "ruSt", "--charset", "utf16le"];
+    assert!(!pngme(&args).status.success());
+    let output = pngme(&[&args[..], &["--output-format", "auto"]].concat());
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "616263\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("showing it as hex"));
+
+    let output = pngme(&["decode", path, "ruSt", "--output-format", "hex"]);
+    assert_eq!(stdout_of(&output), "616263\n");
+}
+
+#[test]
+fn test_decode_only_printable() {
+    let path = scratch_copy("minimal.png", "decode_printable");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruSt", "\x1b]0;owned\x07hi"])
+        .status
+        .success());
+
+    let output = pngme(&["decode", path, "ruSt", "--only-printable"]);
+    assert_eq!(stdout_of(&output), ".]0;owned.hi\n");
+    let output = pngme(&[
+        "decode",
+        path,
+        "ruSt",
+        "--only-printable",
+        "--output-format",
+        "raw",
+    ]);
+    assert_eq!(output.stdout, b"\x1b]0;owned\x07hi");
+}
+
+#[test]
+fn test_decode_expect_crc() {
+    let path = fixture("text.png");
+    let path = path.to_str().unwrap();
+    let crc = Png::from_file(path).unwrap().chunks_by_type("tEXt")[0].crc();
+
+    for expected in [format!("0x{:08X}", crc), crc.to_string()] {
+        let output = pngme(&["decode", path, "tEXt", "--expect-crc", &expected]);
+        assert!(output.status.success());
+        assert_eq!(stdout_of(&output), "Comment: Hello from pngme\n");
+    }
+
+    let output = pngme(&["decode", path, "tEXt", "--expect-crc", "0x12345678"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("expected CRC 0x12345678"));
+    assert!(stderr.contains(&format!("has 0x{:08X}", crc)));
+}
+
+#[test]
+fn test_decode_without_chunk_type_or_default() {
+    let config_home = Scratch::new("no_default", "config");
+    let output = Command::new(env!("CARGO_BIN_EXE_pngme"))
+        .args(["decode", fixture("minimal.png").to_str().unwrap()])
+        .env("XDG_CONFIG_HOME", &*config_home)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("decode "));
+    assert!(stderr.contains("no default_chunk_type configured"));
+}
+
+#[test]
+fn test_decode_show_several_representations() {
+    let path = scratch_copy("minimal.png", "decode_show");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruSt", "hi!"]).status.success());
+
+    let output = pngme(&["decode", path, "ruSt", "--show", "utf8,hex,base64"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout_of(&output),
+        "utf8    hi!\nhex     686921\nbase64  aGkh\n"
+    );
+    let output = pngme(&["decode", path, "ruSt", "--show", "base64"]);
+    assert_eq!(stdout_of(&output), "base64  aGkh\n");
+}
+
+#[test]
+fn test_decode_null_terminated() {
+    let path = fixture("text.png");
+    let path = path.to_str().unwrap();
+    let output = pngme(&["decode", path, "tEXt", "--null-terminated"]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Comment: Hello from pngme\0");
+
+    let output = pngme(&[
+        "decode",
+        path,
+        "tEXt",
+        "--null-terminated",
+        "--output-format",
+        "raw",
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_decode_existing_text_chunk() {
+    let output = pngme(&["decode", fixture("text.png").to_str().unwrap(), "tEXt"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "Comment: Hello from pngme\n");
+}
+
+#[test]
+fn test_encode_text_keyword_round_trip() {
+    let path = scratch_copy("minimal.png", "text_keyword");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "tEXt", "pngme", "--keyword", "Software"]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(
+        png.chunk_by_type("tEXt").unwrap().data(),
+        b"Software\0pngme"
+    );
+
+    let output = pngme(&["decode", path, "tEXt"]);
+    assert_eq!(stdout_of(&output), "Software: pngme\n");
+
+    let long_keyword = "k".repeat(80);
+    let output = pngme(&["encode", path, "tEXt", "x", "--keyword", &long_keyword]);
+    assert!(!output.status.success());
+
+    let output = pngme(&["encode", path, "ruSt", "x", "--keyword", "Title"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_text_is_latin1() {
+    let path = scratch_copy("minimal.png", "text_latin1");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "tEXt", "café", "--keyword", "Title"]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(png.chunk_by_type("tEXt").unwrap().data(), b"Title\0caf\xe9");
+
+    let output = pngme(&["decode", path, "tEXt"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "Title: café\n");
+
+    let output = pngme(&["decode", path, "tEXt", "--charset", "utf8"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not valid utf8"));
+
+    let output = pngme(&["encode", path, "tEXt", "snow ☃", "--keyword", "Title"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_decode_split_round_trip() {
+    let path = scratch_copy("minimal.png", "split_round_trip");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&[
+        "encode",
+        path,
+        "ruSt",
+        "a longer hidden message",
+        "--split",
+        "4",
+    ]);
+    assert!(output.status.success());
+
+    let output = pngme(&["decode", path, "ruSt", "--split"]);
+    assert_eq!(stdout_of(&output), "a longer hidden message\n");
+}
+
+#[test]
+fn test_encode_raw_crc_writes_invalid_file() {
+    let path = scratch_copy("minimal.png", "raw_crc");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["encode", path, "ruSt", "bad crc", "--raw-crc", "0"]);
+    assert!(output.status.success());
+
+    let output = pngme(&["decode", path, "ruSt"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = pngme(&["decode", path, "ruSt", "--no-crc-check"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "bad crc\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning: chunk 2 (ruSt)"));
+}
+
+#[test]
+fn test_remove() {
+    let path = scratch_copy("text.png", "remove");
+
+    let output = pngme(&["remove", path.to_str().unwrap(), "tEXt"]);
+    assert!(output.status.success());
+
+    let png = Png::from_file(&path).unwrap();
+    assert!(png.chunk_by_type("tEXt").is_none());
+    assert!(png.chunk_by_type("IDAT").is_some());
+}
+
+#[test]
+fn test_remove_by_index() {
+    let path = scratch_copy("animated.png", "remove_index");
+    let path = path.to_str().unwrap();
+
+    // The second fcTL sits at index 4, after IHDR, acTL, fcTL and IDAT.
+    let output = pngme(&["remove", path, "--index", "4"]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(png.chunks_by_type("fcTL").len(), 1);
+    assert_eq!(png.chunks()[4].chunk_type_str(), Some("fdAT"));
+
+    let output = pngme(&["remove", path, "--index", "0"]);
+    assert!(!output.status.success());
+    assert!(Png::from_file(path)
+        .unwrap()
+        .chunk_by_type("IHDR")
+        .is_some());
+}
+
+#[test]
+fn test_remove_ancillary() {
+    let path = scratch_copy("animated.png", "remove_ancillary");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["remove", path, "--ancillary"]);
+    assert_eq!(stdout_of(&output), "Removed 4 chunks\n");
+    let types: Vec<String> = Png::from_file(path)
+        .unwrap()
+        .chunks()
+        .iter()
+        .map(|c| c.chunk_type().to_string())
+        .collect();
+    assert_eq!(types, ["IHDR", "IDAT", "IEND"]);
+}
+
+#[test]
+fn test_encode_batch_into_output_dir() {
+    let first = scratch_copy("minimal.png", "batch_encode");
+    let second = scratch_copy("text.png", "batch_encode");
+    let out_dir = first.parent().unwrap().join("out");
+
+    let output = pngme(&[
+        "encode",
+        first.to_str().unwrap(),
+        "ruSt",
+        "batch",
+        "--files",
+        second.to_str().unwrap(),
+        "--output-dir",
+        out_dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(stdout_of(&output).ends_with("2 succeeded, 0 failed, 0 not processed\n"));
+
+    for name in ["minimal.png", "text.png"] {
+        assert_eq!(
+            fs::read(first.parent().unwrap().join(name)).unwrap(),
+            fs::read(fixture(name)).unwrap()
+        );
+        let png = Png::from_file(out_dir.join(name)).unwrap();
+        assert!(png.chunk_by_type("ruSt").is_some());
+    }
+}
+
+#[test]
+fn test_remove_batch_keep_going() {
+    let first = scratch_copy("animated.png", "batch_remove");
+    let second = scratch_copy("minimal.png", "batch_remove");
+    let missing = first.parent().unwrap().join("missing.png");
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    let output = pngme(&[
+        "remove",
+        first,
+        "--ancillary",
+        "--files",
+        missing.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(stdout_of(&output).ends_with("1 succeeded, 1 failed, 0 not processed\n"));
+
+    let output = pngme(&[
+        "remove",
+        missing.to_str().unwrap(),
+        "--ancillary",
+        "--files",
+        second,
+    ]);
+    assert!(!output.status.success());
+    assert!(stdout_of(&output).ends_with("0 succeeded, 1 failed, 1 not processed\n"));
+
+    let output = pngme(&[
+        "remove",
+        missing.to_str().unwrap(),
+        "--ancillary",
+        "--keep-going",
+        "--files",
+        second,
+    ]);
+    assert!(!output.status.success());
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains(&format!("{}: ok", second)));
+    assert!(stdout.ends_with("1 succeeded, 1 failed, 0 not processed\n"));
+}
+
+#[test]
+fn test_remove_unsafe_to_copy_keeps_critical() {
+    let path = scratch_copy("text.png", "remove_unsafe");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruST", "gone", "--force"])
+        .status
+        .success());
+    assert!(pngme(&["encode", path, "ruSt", "stays"]).status.success());
+
+    let output = pngme(&["remove", path, "--unsafe-to-copy"]);
+    let stdout = stdout_of(&output);
+    assert!(
+        stdout.starts_with("Removed 1 chunks\nKept 3 critical chunks"),
+        "{}",
+        stdout
+    );
+    let png = Png::from_file(path).unwrap();
+    assert!(png.chunk_by_type("ruST").is_none());
+    assert!(png.chunk_by_type("ruSt").is_some());
+    assert!(png.chunk_by_type("tEXt").is_some());
+}
+
+#[test]
+fn test_remove_critical_chunk_needs_force() {
+    let path = scratch_copy("minimal.png", "remove_critical");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["remove", path, "IHDR"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+    assert!(Png::from_file(path)
+        .unwrap()
+        .chunk_by_type("IHDR")
+        .is_some());
+
+    let output = pngme(&["remove", path, "IHDR", "--force"]);
+    assert!(output.status.success());
+    assert!(Png::from_file(path)
+        .unwrap()
+        .chunk_by_type("IHDR")
+        .is_none());
+}
+
+#[test]
+fn test_list() {
+    let output = pngme(&["list", fixture("animated.png").to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    for chunk_type in ["IHDR", "acTL", "fcTL", "IDAT", "fdAT", "IEND"] {
+        assert!(
+            stdout.contains(chunk_type),
+            "{} missing from list",
+            chunk_type
+        );
+    }
+}
+
+#[test]
+fn test_list_limit() {
+    let path = fixture("animated.png");
+    let output = pngme(&["list", path.to_str().unwrap(), "--limit", "2"]);
+    let stdout = stdout_of(&output);
+    assert_eq!(stdout.matches("Chunk {").count(), 2);
+    assert!(stdout.ends_with("... and 5 more\n"));
+
+    let output = pngme(&["list", path.to_str().unwrap(), "--limit", "0"]);
+    assert_eq!(stdout_of(&output).matches("Chunk {").count(), 7);
+}
+
+#[test]
+fn test_list_sort() {
+    let path = fixture("animated.png");
+    let path = path.to_str().unwrap();
+    let listed_types = |args: &[&str]| -> Vec<String> {
+        stdout_of(&pngme(&[&["list", path], args].concat()))
+            .lines()
+            .filter_map(|line| line.strip_prefix("  Type: "))
+            .map(str::to_string)
+            .collect()
+    };
+    let largest = Png::from_file(path)
+        .unwrap()
+        .chunks()
+        .iter()
+        .max_by_key(|c| c.length())
+        .unwrap()
+        .chunk_type()
+        .to_string();
+
+    let by_length = listed_types(&["--sort", "length", "--reverse", "--limit", "1"]);
+    assert_eq!(by_length, [largest]);
+    let by_type = listed_types(&["--sort", "type"]);
+    assert_eq!(by_type.first().map(String::as_str), Some("IDAT"));
+    assert_eq!(by_type.last().map(String::as_str), Some("fdAT"));
+    assert_eq!(listed_types(&[]).first().map(String::as_str), Some("IHDR"));
+    assert!(!pngme(&["list", path, "--sort", "size"]).status.success());
+}
+
+#[test]
+fn test_list_crc_format() {
+    let path = fixture("minimal.png");
+    let output = pngme(&["list", path.to_str().unwrap()]);
+    // IEND's CRC is the same in every PNG.
+    assert!(stdout_of(&output).contains("Crc: 0xAE426082\n"));
+    let output = pngme(&["list", path.to_str().unwrap(), "--crc-dec"]);
+    assert!(stdout_of(&output).contains("Crc: 2923585666\n"));
+}
+
+#[test]
+fn test_list_color() {
+    let path = fixture("text.png");
+    let output = pngme(&["list", path.to_str().unwrap(), "--color=always"]);
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains("\x1b[36mChunk {\n  Length: 13"));
+    assert!(stdout.contains("\x1b[33mChunk {"));
+
+    // Output is piped, so auto leaves it plain.
+    let output = pngme(&["list", path.to_str().unwrap()]);
+    assert!(!stdout_of(&output).contains('\x1b'));
+}
+
+#[test]
+fn test_list_hexdump() {
+    let output = pngme(&["list", fixture("text.png").to_str().unwrap(), "--hexdump"]);
+    assert!(output.status.success());
+    assert!(stdout_of(&output).contains("|Comment.Hello fr|"));
+}
+
+#[test]
+fn test_list_keywords() {
+    let output = pngme(&["list-keywords", fixture("text.png").to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "Comment  Hello from pngme\n");
+}
+
+#[test]
+fn test_ztxt_decode_and_list_keywords() {
+    let path = scratch_copy("minimal.png", "ztxt");
+    let mut png = Png::from_file(&path).unwrap();
+    // "Author\0", method 0, then zlib("pngme") at level 9.
+    let data = b"Author\0\0\x78\xda\x2b\xc8\x4b\xcf\x4d\x05\x00\x06\x61\x02\x18".to_vec();
+    png.insert_chunk(1, Chunk::new_ancillary("zTXt", data).unwrap())
+        .unwrap();
+    fs::write(&path, png.as_bytes()).unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["decode", path, "zTXt"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "Author: pngme\n");
+    let output = pngme(&["list-keywords", path]);
+    assert_eq!(stdout_of(&output), "Author  pngme\n");
+}
+
+#[cfg(feature = "image-validate")]
+#[test]
+fn test_validate() {
+    let output = pngme(&["validate", fixture("minimal.png").to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "OK: decoded 1x1 image\n");
+
+    let path = scratch_copy("minimal.png", "validate");
+    let mut png = Png::from_file(&path).unwrap();
+    let idat = png.index_of_type("IDAT").unwrap();
+    png.replace_chunk(
+        idat,
+        Chunk::new("IDAT".parse().unwrap(), b"not zlib".to_vec()),
+    )
+    .unwrap();
+    fs::write(&path, png.as_bytes()).unwrap();
+    let output = pngme(&["validate", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("decoder rejected"));
+}
+
+#[test]
+fn test_verify() {
+    let output = pngme(&["verify", fixture("animated.png").to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "OK: 7 chunks\n");
+
+    let path = scratch_copy("minimal.png", "verify");
+    let mut png = Png::from_file(&path).unwrap();
+    png.append_chunk(pngme::chunk::Chunk::new(
+        "IEND".parse().unwrap(),
+        b"junk".to_vec(),
+    ));
+    fs::write(&path, png.as_bytes()).unwrap();
+
+    let output = pngme(&["verify", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains("IEND at index 3 has 4 bytes, expected 0 bytes"));
+}
+
+#[test]
+fn test_verify_color() {
+    let file = fixture("animated.png");
+    let output = pngme(&["verify", file.to_str().unwrap(), "--color=always"]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "\x1b[32mOK: 7 chunks\x1b[0m\n");
+
+    let path = scratch_copy("minimal.png", "verify_color");
+    let mut png = Png::from_file(&path).unwrap();
+    png.append_chunk(pngme::chunk::Chunk::new(
+        "IEND".parse().unwrap(),
+        b"junk".to_vec(),
+    ));
+    fs::write(&path, png.as_bytes()).unwrap();
+
+    let output = pngme(&["verify", path.to_str().unwrap(), "--color=always"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains("\x1b[31mIEND at index 3 has 4 bytes, expected 0 bytes\x1b[0m"));
+
+    let output = pngme(&["verify", file.to_str().unwrap(), "--color=never"]);
+    assert_eq!(stdout_of(&output), "OK: 7 chunks\n");
+}
+
+#[test]
+fn test_info() {
+    let output = pngme(&["info", fixture("animated.png").to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains("Width: 2"));
+    assert!(stdout.contains("Color type: Grayscale"));
+    assert!(stdout.contains("Animated: yes"));
+    assert!(stdout.contains("Chunks: 7\n  IHDR: image header\n  acTL: animation control\n"));
+    assert_eq!(stdout.matches("fcTL: frame control").count(), 1);
+}
+
+#[test]
+fn test_list_describe() {
+    let path = scratch_copy("minimal.png", "list_describe");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruSt", "hi"]).status.success());
+    let stdout = stdout_of(&pngme(&["list", path, "--describe"]));
+    assert!(stdout.contains("IDAT: image data\n"));
+    assert!(stdout.contains("ruSt: (private/unknown)\n"));
+}
+
+#[test]
+fn test_info_color_chunks() {
+    let path = scratch_copy("minimal.png", "info_color");
+    let output = pngme(&["info", path.to_str().unwrap()]);
+    assert!(!stdout_of(&output).contains("Gamma"));
+
+    let mut png = Png::from_file(&path).unwrap();
+    for (chunk_type, data) in [
+        ("sRGB", vec![0]),
+        ("gAMA", 45455u32.to_be_bytes().to_vec()),
+        ("iCCP", b"Display P3\0\0".to_vec()),
+    ] {
+        let chunk_type = chunk_type.parse().unwrap();
+        png.insert_chunk(1, Chunk::new(chunk_type, data)).unwrap();
+    }
+    fs::write(&path, png.as_bytes()).unwrap();
+
+    let stdout = stdout_of(&pngme(&["info", path.to_str().unwrap()]));
+    assert!(stdout.contains("sRGB: yes, Perceptual rendering intent (0)\n"));
+    assert!(stdout.contains("Gamma: 0.45455\n"));
+    assert!(stdout.contains("ICC profile: Display P3\n"));
+    assert!(!stdout.contains("Chromaticities"));
+}
+
+#[test]
+fn test_extract_all() {
+    let path = scratch_copy("minimal.png", "extract_all");
+    let file = path.to_str().unwrap();
+    pngme(&["encode", file, "ruSt", "first"]);
+    pngme(&["encode", file, "ruSt", "second"]);
+
+    let output_path = path.with_file_name("out.bin");
+    let output = pngme(&[
+        "extract",
+        file,
+        "ruSt",
+        output_path.to_str().unwrap(),
+        "--all",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(stdout_of(&output), "Wrote 2 files\n");
+    assert_eq!(
+        fs::read(path.with_file_name("out.0.bin")).unwrap(),
+        b"first"
+    );
+    assert_eq!(
+        fs::read(path.with_file_name("out.1.bin")).unwrap(),
+        b"second"
+    );
+}
+
+#[test]
+fn test_extract_split() {
+    let path = scratch_copy("minimal.png", "extract_split");
+    let file = path.to_str().unwrap();
+    pngme(&["encode", file, "ruSt", "split across parts", "--split", "5"]);
+
+    let output_path = path.with_file_name("joined.bin");
+    let output = pngme(&[
+        "extract",
+        file,
+        "ruSt",
+        output_path.to_str().unwrap(),
+        "--split",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(fs::read(&output_path).unwrap(), b"split across parts");
+}
+
+#[test]
+fn test_dump() {
+    let path = scratch_copy("text.png", "dump");
+    let out_dir = path.with_file_name("chunks");
+
+    let output = pngme(&["dump", path.to_str().unwrap(), out_dir.to_str().unwrap()]);
+    assert!(output.status.success());
+    let png = Png::from_file(&path).unwrap();
+    assert_eq!(
+        fs::read(out_dir.join("0001_tEXt.bin")).unwrap(),
+        png.chunks()[1].data()
+    );
+    let manifest = fs::read_to_string(out_dir.join("manifest.txt")).unwrap();
+    let lines: Vec<&str> = manifest.lines().skip(1).collect();
+    assert_eq!(lines.len(), png.chunks().len());
+    assert_eq!(
+        lines[1],
+        format!("0001_tEXt.bin tEXt 24 {:08x}", png.chunks()[1].crc())
+    );
+}
+
+#[test]
+fn test_assemble_after_editing_dump() {
+    let path = scratch_copy("text.png", "assemble");
+    let out_dir = path.with_file_name("chunks");
+    let rebuilt = path.with_file_name("rebuilt.png");
+    pngme(&["dump", path.to_str().unwrap(), out_dir.to_str().unwrap()]);
+    fs::write(out_dir.join("0001_tEXt.bin"), b"Comment\0Edited by hand").unwrap();
+
+    let output = pngme(&[
+        "assemble",
+        out_dir.to_str().unwrap(),
+        rebuilt.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let png = Png::from_file(&rebuilt).unwrap();
+    assert_eq!(png.chunks()[1].data(), b"Comment\0Edited by hand");
+    assert_eq!(
+        png.chunks().len(),
+        Png::from_file(&path).unwrap().chunks().len()
+    );
+
+    fs::remove_file(out_dir.join("0000_IHDR.bin")).unwrap();
+    let manifest = fs::read_to_string(out_dir.join("manifest.txt")).unwrap();
+    let without_ihdr: String = manifest
+        .lines()
+        .filter(|line| !line.contains("IHDR"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    fs::write(out_dir.join("manifest.txt"), without_ihdr).unwrap();
+    let output = pngme(&[
+        "assemble",
+        out_dir.to_str().unwrap(),
+        rebuilt.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_encode_keeps_iend_last() {
+    let path = scratch_copy("minimal.png", "iend_last");
+    pngme(&["encode", path.to_str().unwrap(), "ruSt", "hidden message"]);
+
+    let png = Png::from_file(&path).unwrap();
+    let chunks = png.chunks();
+    assert_eq!(chunks[chunks.len() - 2].chunk_type_str(), Some("ruSt"));
+    assert_eq!(chunks[chunks.len() - 1].chunk_type_str(), Some("IEND"));
+}
+
+#[test]
+fn test_copy_chunk() {
+    let dst = scratch_copy("minimal.png", "copy_chunk");
+    let src = fixture("text.png");
+
+    let output = pngme(&[
+        "copy-chunk",
+        src.to_str().unwrap(),
+        dst.to_str().unwrap(),
+        "tEXt",
+    ]);
+    assert!(output.status.success());
+    let copied = Png::from_file(&dst).unwrap();
+    let original = Png::from_file(&src).unwrap();
+    assert_eq!(
+        copied.chunk_by_type("tEXt").unwrap().as_bytes(),
+        original.chunk_by_type("tEXt").unwrap().as_bytes()
+    );
+    assert!(copied.chunks().last().unwrap().is_iend());
+
+    let output = pngme(&[
+        "copy-chunk",
+        src.to_str().unwrap(),
+        dst.to_str().unwrap(),
+        "ruSt",
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_watch_survives_file_being_replaced() {
+    let path = scratch_copy("minimal.png", "watch");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pngme"))
+        .args(["watch", path.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(700));
+    fs::remove_file(&path).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1200));
+    fs::copy(fixture("text.png"), &path).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1200));
+
+    let still_running = child.try_wait().unwrap().is_none();
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(still_running);
+    assert_eq!(stdout_of(&output).matches("--- watching").count(), 2);
+
+    let output = pngme(&[
+        "watch",
+        path.with_file_name("missing.png").to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_rename_type() {
+    let path = scratch_copy("text.png", "rename_type");
+    let output = pngme(&["rename-type", path.to_str().unwrap(), "tEXt", "ruSt"]);
+    assert!(output.status.success());
+
+    let png = Png::from_file(&path).unwrap();
+    assert!(png.chunk_by_type("tEXt").is_none());
+    assert_eq!(png.chunks()[1].chunk_type_str(), Some("ruSt"));
+    assert_eq!(png.chunks()[1].data(), b"Comment\0Hello from pngme");
+
+    let output = pngme(&["rename-type", path.to_str().unwrap(), "re1d", "ruSt"]);
+    assert_eq!(output.status.code(), Some(2));
+    let output = pngme(&["rename-type", path.to_str().unwrap(), "reDd", "ruSt"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_errors_name_the_operation_and_file() {
+    let missing = std::env::temp_dir().join("pngme-no-such-file.png");
+    let missing = missing.to_str().unwrap();
+    let output = pngme(&["encode", missing, "ruSt", "x"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with(&format!("Error: encode into {}: ", missing)));
+    assert_eq!(stderr.lines().count(), 1);
+
+    let output = pngme(&["decode", fixture("minimal.png").to_str().unwrap(), "ruSt"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("minimal.png: no ruSt chunk found"));
+}
+
+#[test]
+fn test_decode_exit_codes() {
+    let output = pngme(&["decode", fixture("minimal.png").to_str().unwrap(), "ruSt"]);
+    assert_eq!(output.status.code(), Some(3));
+
+    let not_a_png = fixture("../cli.rs");
+    let output = pngme(&["decode", not_a_png.to_str().unwrap(), "ruSt"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = pngme(&["decode", "/nonexistent/pngme.png", "ruSt"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_remove_missing_chunk_leaves_file_unchanged() {
+    let path = scratch_copy("minimal.png", "remove_missing");
+    let output = pngme(&["remove", path.to_str().unwrap(), "ruSt"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        fs::read(&path).unwrap(),
+        fs::read(fixture("minimal.png")).unwrap()
+    );
+}
+
+#[test]
+fn test_diff_spots_injected_chunk() {
+    let path = scratch_copy("minimal.png", "diff");
+    pngme(&["encode", path.to_str().unwrap(), "ruSt", "hidden message"]);
+
+    let output = pngme(&[
+        "diff",
+        fixture("minimal.png").to_str().unwrap(),
+        path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains("only-b   ruSt  b#2"));
+    assert!(!stdout.contains("differs"));
+}
+
+#[test]
+fn test_checksum_tracks_file_changes() {
+    let path = scratch_copy("minimal.png", "checksum");
+    let path = path.to_str().unwrap();
+    let before = stdout_of(&pngme(&["checksum", path]));
+    assert_eq!(before.len(), 8 + 2 + path.len() + 1);
+    assert!(before.ends_with(&format!("  {}\n", path)));
+
+    assert!(pngme(&["print", path]).status.success());
+    assert_eq!(stdout_of(&pngme(&["checksum", path])), before);
+
+    assert!(pngme(&["encode", path, "ruSt", "hi"]).status.success());
+    assert_ne!(stdout_of(&pngme(&["checksum", path])), before);
+}
+
+#[test]
+fn test_encode_after() {
+    let path = scratch_copy("minimal.png", "after");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&[
+        "encode", path, "ruSt", "hi", "--after", "IHDR", "--split", "1",
+    ]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    let types: Vec<&str> = png
+        .chunks()
+        .iter()
+        .map(|c| c.chunk_type_str().unwrap())
+        .collect();
+    assert_eq!(types, ["IHDR", "ruSt", "ruSt", "IDAT", "IEND"]);
+
+    let output = pngme(&["encode", path, "ruSt", "hi", "--after", "tEXt"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_encode_before() {
+    let path = scratch_copy("minimal.png", "before");
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&[
+        "encode", path, "ruSt", "hi", "--before", "IDAT", "--split", "1",
+    ]);
+    assert!(output.status.success());
+    let output = pngme(&["encode", path, "ruSt", "again", "--after", "ruSt"]);
+    assert!(output.status.success());
+    let png = Png::from_file(path).unwrap();
+    let types: Vec<&str> = png
+        .chunks()
+        .iter()
+        .map(|c| c.chunk_type_str().unwrap())
+        .collect();
+    assert_eq!(types, ["IHDR", "ruSt", "ruSt", "ruSt", "IDAT", "IEND"]);
+    assert_eq!(png.chunks()[2].data(), b"again");
+
+    let output = pngme(&["encode", path, "ruSt", "hi", "--before", "tEXt"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_find_count() {
+    let path = scratch_copy("minimal.png", "find_count");
+    let path = path.to_str().unwrap();
+    assert!(
+        pngme(&["encode", path, "ruSt", "needle needle", "--split", "7"])
+            .status
+            .success()
+    );
+
+    let output = pngme(&["find", path, "needle"]);
+    assert_eq!(stdout_of(&output).lines().count(), 2);
+    let output = pngme(&["find", path, "needle", "--count"]);
+    assert_eq!(stdout_of(&output), "ruSt: 2\n");
+}
+
+#[test]
+fn test_find_type_filter_case() {
+    let path = scratch_copy("minimal.png", "find_type");
+    let path = path.to_str().unwrap();
+    assert!(pngme(&["encode", path, "ruSt", "needle"]).status.success());
+    assert!(pngme(&["encode", path, "prVt", "needle"]).status.success());
+
+    let output = pngme(&["find", path, "needle", "--type", "rust"]);
+    assert_eq!(stdout_of(&output), "No matches\n");
+    let output = pngme(&[
+        "find",
+        path,
+        "needle",
+        "--type",
+        "rust",
+        "--ignore-type-case",
+    ]);
+    assert_eq!(stdout_of(&output), "ruSt at index 2, offset 0\n");
+}
+
+#[test]
+fn test_empty_file_reports_too_small() {
+    let dir = scratch_copy("minimal.png", "empty_file");
+    let path = dir.with_file_name("empty.png");
+    fs::write(&path, b"").unwrap();
+
+    let output = pngme(&["info", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("too small to be a PNG"));
+}
+
+#[test]
+fn test_fingerprint_changes_with_structure_only() {
+    let path = scratch_copy("text.png", "fingerprint");
+    let path = path.to_str().unwrap();
+    let before = stdout_of(&pngme(&["fingerprint", path]));
+    assert_eq!(before.trim_end().len(), 8);
+
+    // Same length, different text: the layout is unchanged.
+    let mut png = Png::from_file(path).unwrap();
+    let index = png.index_of_type("tEXt").unwrap();
+    let mut data = png.chunks()[index].data().to_vec();
+    data.iter_mut().rev().take(3).for_each(|b| *b = b'x');
+    png.chunks_mut()[index].set_data(data).unwrap();
+    fs::write(path, png.as_bytes()).unwrap();
+    assert_eq!(stdout_of(&pngme(&["fingerprint", path])), before);
+
+    assert!(pngme(&["encode", path, "ruSt", "hidden"]).status.success());
+    assert_ne!(stdout_of(&pngme(&["fingerprint", path])), before);
+}
+
+#[test]
+fn test_stats() {
+    let output = pngme(&["stats", fixture("animated.png").to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    assert!(
+        stdout.starts_with("Chunk types: fcTL: 2, IDAT: 1, IEND: 1, IHDR: 1, acTL: 1, fdAT: 1\n")
+    );
+    assert!(stdout.contains("Data bytes by type:\n  fcTL: 52\n  fdAT: 18\n"));
+}
+
+#[test]
+fn test_offset_reads_embedded_png() {
+    let dir = scratch_copy("text.png", "offset");
+    let path = dir.with_file_name("carved.bin");
+    let mut bytes = vec![0xAB; 100];
+    bytes.extend(fs::read(fixture("text.png")).unwrap());
+    fs::write(&path, bytes).unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = pngme(&["decode", path, "tEXt", "--offset", "100"]);
+    assert_eq!(stdout_of(&output), "Comment: Hello from pngme\n");
+    let output = pngme(&["list", path, "--offset", "100"]);
+    assert!(stdout_of(&output).contains("tEXt"));
+
+    let output = pngme(&["decode", path, "tEXt", "--offset", "99"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no PNG signature at offset 99"));
+}
+
+#[test]
+fn test_scan_concatenated_pngs() {
+    let dir = scratch_copy("minimal.png", "scan");
+    let path = dir.with_file_name("bundle.bin");
+    let minimal = fs::read(fixture("minimal.png")).unwrap();
+    let mut bytes = b"header".to_vec();
+    bytes.extend(&minimal);
+    bytes.extend(&minimal[..20]);
+    fs::write(&path, bytes).unwrap();
+
+    let output = pngme(&["scan", path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        format!("offset 6: valid PNG, 3 chunks, {} bytes", minimal.len())
+    );
+    assert!(lines[1].starts_with(&format!("offset {}: does not parse", 6 + minimal.len())));
+}
+
+#[test]
+fn test_scan_nested_png() {
+    let path = scratch_copy("minimal.png", "scan_nested");
+    let minimal = fs::read(fixture("minimal.png")).unwrap();
+    let mut outer = Png::from_file(&*path).unwrap();
+    outer.encode_message("ruSt", &minimal).unwrap();
+    let outer = outer.as_bytes();
+    fs::write(&path, &outer).unwrap();
+    let inner_offset = Png::find_signatures(&outer)[1];
+
+    let output = pngme(&["scan", path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            format!("offset 0: valid PNG, 4 chunks, {} bytes", outer.len()),
+            format!(
+                "offset {}: valid PNG, 3 chunks, {} bytes",
+                inner_offset,
+                minimal.len()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_encode_skip_duplicate() {
+    let path = scratch_copy("minimal.png", "skip_duplicate");
+    let path = path.to_str().unwrap();
+    for _ in 0..2 {
+        let output = pngme(&["encode", path, "ruSt", "same", "--skip-duplicate"]);
+        assert!(output.status.success());
+    }
+    let output = pngme(&["encode", path, "ruSt", "same", "--skip-duplicate"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already holds this ruSt payload"));
+    assert!(
+        pngme(&["encode", path, "ruSt", "other", "--skip-duplicate"])
+            .status
+            .success()
+    );
+
+    let png = Png::from_file(path).unwrap();
+    assert_eq!(png.chunks_by_type("ruSt").len(), 2);
+}